@@ -22,7 +22,7 @@ async fn main() -> Result<(), anyhow::Error> {
             Arg::new("bip32-path")
                 .short('p')
                 .long("path")
-                .help("bip32 path to use (default \"m/44'/4218'/0'/0'/0'\")")
+                .help("derivation path or account/index shorthand (default \"m/44'/4218'/0'/0'/0'\")")
                 .value_name("PATH")
                 .required(false),
         )
@@ -39,9 +39,24 @@ async fn main() -> Result<(), anyhow::Error> {
                 .help("transaction bytes in base64 format")
                 .required(true),
         )
+        .arg(
+            Arg::new("is-simulator")
+                .short('s')
+                .long("simulator")
+                .help("select the simulator as transport")
+                .action(clap::ArgAction::SetTrue)
+                .required(false),
+        )
+        .arg(
+            Arg::new("simulator-address")
+                .long("simulator-address")
+                .value_name("HOST:PORT")
+                .help("simulator address, implies --simulator (default $LEDGER_SIMULATOR_ADDR or 127.0.0.1:9999)")
+                .required(false),
+        )
         .get_matches();
 
-    let derivation_path = bip32::DerivationPath::from_str(
+    let derivation_path = iota_ledger::IotaDerivationPath::from_str(
         matches
             .get_one::<String>("bip32-path")
             .map(|s| s.as_str())
@@ -73,7 +88,13 @@ async fn main() -> Result<(), anyhow::Error> {
         println!("No IOTA network specified, only blind-signing supported.");
     }
 
-    let ledger = iota_ledger::get_ledger_by_type(iota_ledger::TransportTypes::NativeHID)?;
+    let is_simulator = matches.get_flag("is-simulator");
+    let simulator_address = matches.get_one::<String>("simulator-address");
+
+    let transport_type =
+        iota_ledger::TransportTypes::select(is_simulator, simulator_address.map(String::as_str))?;
+
+    let ledger = iota_ledger::get_ledger_by_type(transport_type)?;
 
     let signer = iota_ledger_signer::IotaLedgerSigner::new(ledger, derivation_path, client);
 
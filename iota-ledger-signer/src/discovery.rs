@@ -0,0 +1,111 @@
+use iota_ledger::{IotaDerivationPath, LedgerHardwareWallet, PathTemplate};
+use iota_sdk::{
+    IotaClient,
+    rpc_types::{IotaTransactionBlockResponseQuery, TransactionFilter},
+    types::base_types::IotaAddress,
+};
+
+/// Settings for
+/// [`IotaLedgerSigner::discover_accounts`](crate::IotaLedgerSigner::discover_accounts)
+#[derive(Clone, Debug)]
+pub struct DiscoveryOptions {
+    pub template: PathTemplate,
+    /// Consecutive unused addresses after which an account is considered done
+    pub gap_limit: u32,
+    /// Consecutive accounts without any used address after which discovery
+    /// stops
+    pub account_gap_limit: u32,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        DiscoveryOptions {
+            template: PathTemplate::default(),
+            gap_limit: 20,
+            account_gap_limit: 1,
+        }
+    }
+}
+
+/// A used address found during discovery
+#[derive(Clone, Debug)]
+pub struct DiscoveredAddress {
+    pub account: u32,
+    pub index: u32,
+    pub path: IotaDerivationPath,
+    pub address: IotaAddress,
+    /// Total IOTA balance in nanos
+    pub balance: u128,
+}
+
+// an address is used if it owns objects or ever sent a transaction
+async fn is_used(client: &IotaClient, address: IotaAddress) -> Result<bool, anyhow::Error> {
+    let objects = client
+        .read_api()
+        .get_owned_objects(address, None, None, Some(1))
+        .await?;
+    if !objects.data.is_empty() {
+        return Ok(true);
+    }
+
+    let query =
+        IotaTransactionBlockResponseQuery::new(Some(TransactionFilter::FromAddress(address)), None);
+    let transactions = client
+        .read_api()
+        .query_transaction_blocks(query, None, Some(1), false)
+        .await?;
+    Ok(!transactions.data.is_empty())
+}
+
+pub(crate) async fn discover(
+    ledger: &LedgerHardwareWallet,
+    client: &IotaClient,
+    options: &DiscoveryOptions,
+) -> Result<Vec<DiscoveredAddress>, anyhow::Error> {
+    let mut discovered = Vec::new();
+    let mut unused_accounts = 0;
+
+    for account in 0.. {
+        if unused_accounts >= options.account_gap_limit {
+            break;
+        }
+
+        let mut account_used = false;
+        let mut gap = 0;
+        for index in 0.. {
+            if gap >= options.gap_limit {
+                break;
+            }
+
+            let path = options.template.derive(account, index)?;
+            let address = ledger.get_public_key(&path)?.address;
+            if !is_used(client, address).await? {
+                gap += 1;
+                continue;
+            }
+
+            gap = 0;
+            account_used = true;
+            let balance = client
+                .coin_read_api()
+                .get_balance(address, None)
+                .await?
+                .total_balance;
+            discovered.push(DiscoveredAddress {
+                account,
+                index,
+                path,
+                address,
+                balance,
+            });
+        }
+
+        if account_used {
+            unused_accounts = 0;
+        } else {
+            unused_accounts += 1;
+        }
+    }
+
+    Ok(discovered)
+}
@@ -1,22 +1,44 @@
 use fastcrypto::ed25519::Ed25519PublicKey;
-use iota_ledger::{LedgerHardwareWallet, SignedTransaction};
+use iota_ledger::{IotaDerivationPath, LedgerHardwareWallet, SearchBounds, SignedTransaction};
 use iota_sdk::{
     IotaClient,
     types::{base_types::IotaAddress, crypto::SignatureScheme, transaction::TransactionData},
 };
 use shared_crypto::intent::{Intent, IntentMessage};
+mod discovery;
 mod utils;
 
+pub use discovery::{DiscoveredAddress, DiscoveryOptions};
+
+/// Progress of [`IotaLedgerSigner::sign_transactions`], `index` is zero based
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchProgress {
+    /// The transaction is being signed, the device will ask for confirmation
+    Signing {
+        index: usize,
+        total: usize,
+    },
+    Signed {
+        index: usize,
+        total: usize,
+    },
+    /// Rejected or failed, the remaining transactions are still signed
+    Failed {
+        index: usize,
+        total: usize,
+    },
+}
+
 pub struct IotaLedgerSigner {
     pub client: Option<IotaClient>,
-    pub path: bip32::DerivationPath,
+    pub path: IotaDerivationPath,
     pub ledger: LedgerHardwareWallet,
 }
 
 impl IotaLedgerSigner {
     pub fn new(
         ledger: LedgerHardwareWallet,
-        path: bip32::DerivationPath,
+        path: IotaDerivationPath,
         client: Option<IotaClient>,
     ) -> Self {
         IotaLedgerSigner {
@@ -26,6 +48,19 @@ impl IotaLedgerSigner {
         }
     }
 
+    /// Create a signer for `address`, searching its path within `bounds`
+    pub fn from_address(
+        ledger: LedgerHardwareWallet,
+        address: IotaAddress,
+        bounds: &SearchBounds,
+        client: Option<IotaClient>,
+    ) -> Result<Self, anyhow::Error> {
+        let path = ledger
+            .find_path(&address, bounds)?
+            .ok_or_else(|| anyhow::anyhow!("no path within the search bounds derives {address}"))?;
+        Ok(Self::new(ledger, path, client))
+    }
+
     pub fn get_signature_scheme(&self) -> SignatureScheme {
         self.ledger.get_signature_scheme()
     }
@@ -56,6 +91,43 @@ impl IotaLedgerSigner {
             .map_err(anyhow::Error::from)
     }
 
+    /// Sign several transactions in one device session
+    ///
+    /// Returns one result per transaction in the same order, a failed item
+    /// doesn't affect the others.
+    pub async fn sign_transactions(
+        &self,
+        transactions: Vec<TransactionData>,
+        mut progress: impl FnMut(BatchProgress),
+    ) -> Vec<Result<SignedTransaction<TransactionData>, anyhow::Error>> {
+        let total = transactions.len();
+        let mut results = Vec::with_capacity(total);
+
+        for (index, transaction) in transactions.into_iter().enumerate() {
+            progress(BatchProgress::Signing { index, total });
+            let result = self.sign_transaction(transaction).await;
+            progress(match result {
+                Ok(_) => BatchProgress::Signed { index, total },
+                Err(_) => BatchProgress::Failed { index, total },
+            });
+            results.push(result);
+        }
+        results
+    }
+
+    /// Find the used addresses of the ledger by asking the node about each
+    /// derived address, see [`DiscoveryOptions`]
+    pub async fn discover_accounts(
+        &self,
+        options: &DiscoveryOptions,
+    ) -> Result<Vec<DiscoveredAddress>, anyhow::Error> {
+        let client = self
+            .client
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("account discovery requires an IotaClient"))?;
+        discovery::discover(&self.ledger, client, options).await
+    }
+
     pub fn sign_message(
         &self,
         message: Vec<u8>,
@@ -1,4 +1,4 @@
-use std::{error::Error, thread, time};
+use std::{error::Error, time::Duration};
 
 use clap::{Arg, Command};
 
@@ -15,25 +15,25 @@ pub fn main() -> Result<(), Box<dyn Error>> {
                 .action(clap::ArgAction::SetTrue)
                 .required(false),
         )
+        .arg(
+            Arg::new("simulator-address")
+                .long("simulator-address")
+                .value_name("HOST:PORT")
+                .help("simulator address, implies --simulator (default $LEDGER_SIMULATOR_ADDR or 127.0.0.1:9999)")
+                .required(false),
+        )
         .get_matches();
 
     let is_simulator = matches.get_flag("is-simulator");
+    let simulator_address = matches.get_one::<String>("simulator-address");
 
-    let transport_type = if is_simulator {
-        iota_ledger::TransportTypes::TCP
-    } else {
-        iota_ledger::TransportTypes::NativeHID
-    };
+    let transport_type =
+        iota_ledger::TransportTypes::select(is_simulator, simulator_address.map(String::as_str))?;
 
     let ledger: iota_ledger::LedgerHardwareWallet =
         iota_ledger::get_ledger_by_type(transport_type)?;
 
-    if ledger.is_app_open()? {
-        println!("App is already open");
-    } else {
-        ledger.bolos_open_app()?;
-        thread::sleep(time::Duration::from_secs(5));
-    }
+    ledger.ensure_app_open(Duration::from_secs(60), |progress| println!("{progress}"))?;
     let version = ledger.get_version()?;
     println!("current app version: {version}");
     Ok(())
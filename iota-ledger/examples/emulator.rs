@@ -0,0 +1,31 @@
+use std::{error::Error, str::FromStr};
+
+use iota_ledger::{
+    LedgerHardwareWallet,
+    transport::{EmulatorConfig, TransportEmulator},
+};
+use iota_types::crypto::IotaSignature;
+use shared_crypto::intent::{Intent, IntentMessage};
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    let emulator = TransportEmulator::new(EmulatorConfig::default())?;
+    let ledger = LedgerHardwareWallet::with_transport(emulator);
+
+    println!("current app version: {}", ledger.get_version()?);
+
+    let derivation_path = iota_ledger::IotaDerivationPath::from_str("m/44'/4218'/0'/0'/0'")?;
+    let public_key = ledger.get_public_key(&derivation_path)?;
+    println!("Public Key: {}", hex::encode(&public_key.public_key));
+    println!("Address: {}", public_key.address);
+
+    let intent_msg = IntentMessage::new(Intent::personal_message(), b"Hello IOTA".to_vec());
+    let signed = ledger.sign_intent(&derivation_path, intent_msg, vec![])?;
+    signed.signature.verify_secure(
+        &signed.intent_msg,
+        signed.address,
+        signed.signature.scheme(),
+    )?;
+    println!("Signature verified");
+
+    Ok(())
+}
@@ -9,7 +9,7 @@ pub fn main() -> Result<(), Box<dyn Error>> {
             Arg::new("bip32-path")
                 .short('p')
                 .long("path")
-                .help("bip32 path to use (default \"m/44'/4218'/0'/0'/0'\")")
+                .help("derivation path or account/index shorthand (default \"m/44'/4218'/0'/0'/0'\")")
                 .value_name("PATH")
                 .required(false),
         )
@@ -28,11 +28,18 @@ pub fn main() -> Result<(), Box<dyn Error>> {
                 .action(clap::ArgAction::SetTrue)
                 .required(false),
         )
+        .arg(
+            Arg::new("simulator-address")
+                .long("simulator-address")
+                .value_name("HOST:PORT")
+                .help("simulator address, implies --simulator (default $LEDGER_SIMULATOR_ADDR or 127.0.0.1:9999)")
+                .required(false),
+        )
         .get_matches();
 
     let is_simulator = matches.get_flag("is-simulator");
 
-    let derivation_path = bip32::DerivationPath::from_str(
+    let derivation_path = iota_ledger::IotaDerivationPath::from_str(
         matches
             .get_one::<String>("bip32-path")
             .map(|s| s.as_str())
@@ -41,11 +48,10 @@ pub fn main() -> Result<(), Box<dyn Error>> {
 
     let verify = matches.get_flag("verify");
 
-    let transport_type = if is_simulator {
-        iota_ledger::TransportTypes::TCP
-    } else {
-        iota_ledger::TransportTypes::NativeHID
-    };
+    let simulator_address = matches.get_one::<String>("simulator-address");
+
+    let transport_type =
+        iota_ledger::TransportTypes::select(is_simulator, simulator_address.map(String::as_str))?;
 
     let ledger = iota_ledger::get_ledger_by_type(transport_type)?;
 
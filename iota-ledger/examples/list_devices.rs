@@ -0,0 +1,19 @@
+use std::error::Error;
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    let devices = iota_ledger::transport::list_devices()?;
+    if devices.is_empty() {
+        println!("No Ledger devices found");
+    }
+
+    for device in devices {
+        println!(
+            "{} (product id {:#06x}, serial number {}): {}",
+            device.model,
+            device.product_id,
+            device.serial_number.as_deref().unwrap_or("-"),
+            device.path
+        );
+    }
+    Ok(())
+}
@@ -1,6 +1,11 @@
 use std::{error::Error, str::FromStr};
 
 use clap::{Arg, Command};
+use iota_ledger::{
+    TransportOptions,
+    api::constants::APDUInstructions,
+    transport::{ApduEvent, ApduObserver, BlocksProgress, BlocksStage},
+};
 use iota_types::{crypto::EncodeDecodeBase64, object::Object, transaction::TransactionData};
 use shared_crypto::intent::IntentMessage;
 
@@ -16,6 +21,35 @@ fn object_from_base64(b64: &str) -> Object {
     bcs::from_bytes(&bytes).expect("Invalid bcs in object")
 }
 
+/// Shows the transfer progress and asks for confirmation once the
+/// transaction is on the device
+struct ConfirmPrompt;
+
+impl ApduObserver for ConfirmPrompt {
+    fn blocks_progress(&self, progress: &BlocksProgress) {
+        if progress.instruction != APDUInstructions::SignTransaction {
+            return;
+        }
+        match progress.stage {
+            BlocksStage::Sending => println!(
+                "Sending chunk {}/{} ({}/{} bytes)",
+                progress.chunks_served,
+                progress.chunks_total,
+                progress.bytes_served,
+                progress.bytes_total
+            ),
+            BlocksStage::AwaitingConfirmation => {
+                println!("Please review and confirm the transaction on your device")
+            }
+            BlocksStage::Done => {}
+        }
+    }
+
+    fn after_exchange(&self, event: &ApduEvent<'_>) {
+        log::debug!("{:?} took {:?}", event.instruction, event.elapsed);
+    }
+}
+
 pub fn main() -> Result<(), Box<dyn Error>> {
     let matches = Command::new("iota-ledger-cli")
         .version("1.0")
@@ -23,7 +57,7 @@ pub fn main() -> Result<(), Box<dyn Error>> {
             Arg::new("bip32-path")
                 .short('p')
                 .long("path")
-                .help("bip32 path to use (default \"m/44'/4218'/0'/0'/0'\")")
+                .help("derivation path or account/index shorthand (default \"m/44'/4218'/0'/0'/0'\")")
                 .value_name("PATH")
                 .required(false),
         )
@@ -50,11 +84,18 @@ pub fn main() -> Result<(), Box<dyn Error>> {
                 .action(clap::ArgAction::Append)
                 .required(false),
         )
+        .arg(
+            Arg::new("simulator-address")
+                .long("simulator-address")
+                .value_name("HOST:PORT")
+                .help("simulator address, implies --simulator (default $LEDGER_SIMULATOR_ADDR or 127.0.0.1:9999)")
+                .required(false),
+        )
         .get_matches();
 
     let is_simulator = matches.get_flag("is-simulator");
 
-    let derivation_path = bip32::DerivationPath::from_str(
+    let derivation_path = iota_ledger::IotaDerivationPath::from_str(
         matches
             .get_one::<String>("bip32-path")
             .map(|s| s.as_str())
@@ -66,11 +107,10 @@ pub fn main() -> Result<(), Box<dyn Error>> {
         .map(|objs| objs.map(|o| object_from_base64(o)).collect())
         .unwrap_or_default();
 
-    let transport_type = if is_simulator {
-        iota_ledger::TransportTypes::TCP
-    } else {
-        iota_ledger::TransportTypes::NativeHID
-    };
+    let simulator_address = matches.get_one::<String>("simulator-address");
+
+    let transport_type =
+        iota_ledger::TransportTypes::select(is_simulator, simulator_address.map(String::as_str))?;
 
     let transaction = intent_from_base64(
         matches
@@ -78,7 +118,8 @@ pub fn main() -> Result<(), Box<dyn Error>> {
             .expect("Transaction bytes are required"),
     );
 
-    let ledger = iota_ledger::get_ledger_by_type(transport_type)?;
+    let options = TransportOptions::default().with_observer(ConfirmPrompt);
+    let ledger = iota_ledger::get_ledger_with_options(transport_type, &options)?;
 
     let signature = ledger.sign_intent(&derivation_path, transaction, objects)?;
     println!("Signature: {}", &signature.signature.encode_base64());
@@ -17,10 +17,40 @@ use crate::{
 // HID <= 0104|494f5441|05|302e372e30|0102|9000
 // I O T A      0 . 7 . 0
 
-#[derive(Debug)]
+/// Device is in recovery mode
+pub(crate) const OS_FLAG_RECOVERY: u8 = 1 << 0;
+/// Running app is signed by Ledger
+pub(crate) const OS_FLAG_SIGNED_APP: u8 = 1 << 1;
+/// Device is set up
+pub(crate) const OS_FLAG_ONBOARDED: u8 = 1 << 2;
+/// PIN was entered since power on
+pub(crate) const OS_FLAG_PIN_VALIDATED: u8 = 1 << 7;
+
+/// Name and version of the running app, `BOLOS` for the dashboard
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Response {
     pub app: String,
     pub version: String,
+    /// Raw OS flags, 0 if not reported
+    pub flags: u8,
+}
+
+impl Response {
+    pub fn recovery(&self) -> bool {
+        self.flags & OS_FLAG_RECOVERY != 0
+    }
+
+    pub fn signed_app(&self) -> bool {
+        self.flags & OS_FLAG_SIGNED_APP != 0
+    }
+
+    pub fn onboarded(&self) -> bool {
+        self.flags & OS_FLAG_ONBOARDED != 0
+    }
+
+    pub fn pin_validated(&self) -> bool {
+        self.flags & OS_FLAG_PIN_VALIDATED != 0
+    }
 }
 
 impl Unpackable for Response {
@@ -34,19 +64,29 @@ impl Unpackable for Response {
         let app = String::unpack(buf)?;
         let version = String::unpack(buf)?;
 
+        // length prefixed flags, missing on some nano s firmwares
+        let flags = match u8::unpack(buf) {
+            Ok(len) if len > 0 => u8::unpack(buf)?,
+            _ => 0,
+        };
+
         // consume all extra bytes (nano x <-> nano s compatibility!)
         while u8::unpack(buf).is_ok() {
             // NOP
         }
 
-        Ok(Self { app, version })
+        Ok(Self {
+            app,
+            version,
+            flags,
+        })
     }
 }
 
 pub fn exec(transport: &Transport) -> Result<Response, errors::LedgerError> {
     let cmd = APDUCommand {
         cla: constants::APDU_BOLOS_CLA_B0,
-        ins: constants::APDUInstructionsBolos::GetAppVersionB0 as u8,
+        ins: constants::APDUInstructionsBolos::GetAppVersionB0.ins(),
         p1: 0,
         p2: 0,
         data: Vec::new(),
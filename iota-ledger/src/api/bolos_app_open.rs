@@ -37,7 +37,7 @@ pub fn exec(transport: &Transport, app: String) -> Result<(), errors::LedgerErro
 
     let cmd = APDUCommand {
         cla: constants::APDU_BOLOS_CLA_E0,
-        ins: constants::APDUInstructionsBolos::OpenAppE0 as u8,
+        ins: constants::APDUInstructionsBolos::OpenAppE0.ins(),
         p1: 0,
         p2: 0,
         data: buf,
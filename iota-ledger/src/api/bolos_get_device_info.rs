@@ -0,0 +1,62 @@
+use ledger_transport::APDUCommand;
+
+use crate::{
+    Transport,
+    api::{
+        constants, errors, helpers,
+        packable::{Error as PackableError, Read, Unpackable},
+    },
+};
+// dashboard only:
+// HID => e001000000
+// HID <= 33100004|05|312e312e31|04|a6000000|04|342e3033|9000
+//        target   1 . 1 . 1    flags         4 . 0 3
+
+#[derive(Debug)]
+pub struct Response {
+    pub target_id: u32,
+    pub se_version: String,
+    pub flags: Vec<u8>,
+    pub mcu_version: String,
+}
+
+impl Unpackable for Response {
+    fn unpack<R: Read>(buf: &mut R) -> Result<Self, PackableError>
+    where
+        Self: Sized,
+    {
+        let mut target_id = [0u8; 4];
+        buf.read_exact(&mut target_id)?;
+        let se_version = String::unpack(buf)?;
+
+        let flags_len = u8::unpack(buf)?;
+        let mut flags = vec![0u8; flags_len as usize];
+        buf.read_exact(&mut flags)?;
+
+        // some firmwares terminate the mcu version with a zero byte
+        let mcu_version = String::unpack(buf)?.trim_end_matches('\0').to_string();
+
+        // consume all extra bytes (bootloader and hardware versions)
+        while u8::unpack(buf).is_ok() {
+            // NOP
+        }
+
+        Ok(Self {
+            target_id: u32::from_be_bytes(target_id),
+            se_version,
+            flags,
+            mcu_version,
+        })
+    }
+}
+
+pub fn exec(transport: &Transport) -> Result<Response, errors::LedgerError> {
+    let cmd = APDUCommand {
+        cla: constants::APDU_BOLOS_CLA_E0,
+        ins: constants::APDUInstructionsBolos::GetDeviceInfoE0.ins(),
+        p1: 0,
+        p2: 0,
+        data: Vec::new(),
+    };
+    helpers::exec::<Response>(transport, cmd)
+}
@@ -1,5 +1,6 @@
 pub const HARDENED: u32 = 0x80000000;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum APDUInstructions {
     GetVersion = 0x00,
     VerifyAddress = 0x01,
@@ -12,6 +13,23 @@ pub enum APDUInstructions {
     Exit = 0xff,
 }
 
+impl TryFrom<u8> for APDUInstructions {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(APDUInstructions::GetVersion),
+            0x01 => Ok(APDUInstructions::VerifyAddress),
+            0x02 => Ok(APDUInstructions::GetPublicKey),
+            0x03 => Ok(APDUInstructions::SignTransaction),
+            0x10 => Ok(APDUInstructions::GetAppConfig),
+            0x11 => Ok(APDUInstructions::SetAccount),
+            0xff => Ok(APDUInstructions::Exit),
+            _ => Err(value),
+        }
+    }
+}
+
 pub(crate) const APDU_CLA: u8 = 0x00;
 pub(crate) const APDU_P1: u8 = 0x00;
 pub(crate) const APDU_P2: u8 = 0x00;
@@ -19,9 +37,41 @@ pub(crate) const APDU_P2: u8 = 0x00;
 pub(crate) const APDU_BOLOS_CLA_B0: u8 = 0xb0;
 pub(crate) const APDU_BOLOS_CLA_E0: u8 = 0xe0;
 
-pub(crate) enum APDUInstructionsBolos {
-    GetAppVersionB0 = 0x01,
-    AppExitB0 = 0xa7,
+/// Instructions of the dashboard / OS, the same byte is used in both classes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum APDUInstructionsBolos {
+    GetAppVersionB0,
+    AppExitB0,
+
+    GetDeviceInfoE0,
+    OpenAppE0,
+}
+
+impl APDUInstructionsBolos {
+    pub const ALL: [APDUInstructionsBolos; 4] = [
+        APDUInstructionsBolos::GetAppVersionB0,
+        APDUInstructionsBolos::AppExitB0,
+        APDUInstructionsBolos::GetDeviceInfoE0,
+        APDUInstructionsBolos::OpenAppE0,
+    ];
+
+    pub fn cla(self) -> u8 {
+        match self {
+            APDUInstructionsBolos::GetAppVersionB0 | APDUInstructionsBolos::AppExitB0 => {
+                APDU_BOLOS_CLA_B0
+            }
+            APDUInstructionsBolos::GetDeviceInfoE0 | APDUInstructionsBolos::OpenAppE0 => {
+                APDU_BOLOS_CLA_E0
+            }
+        }
+    }
 
-    OpenAppE0 = 0xd8,
+    pub fn ins(self) -> u8 {
+        match self {
+            APDUInstructionsBolos::GetAppVersionB0 => 0x01,
+            APDUInstructionsBolos::AppExitB0 => 0xa7,
+            APDUInstructionsBolos::GetDeviceInfoE0 => 0x01,
+            APDUInstructionsBolos::OpenAppE0 => 0xd8,
+        }
+    }
 }
@@ -1,6 +1,10 @@
+use iota_types::base_types::IotaAddress;
 pub use ledger_transport::APDUErrorCode;
+use shared_crypto::intent::IntentScope;
 use thiserror::Error;
 
+use crate::{Version, config::Feature};
+
 #[derive(Debug)]
 #[repr(u8)]
 pub enum SyscallError {
@@ -16,10 +20,22 @@ pub enum SyscallError {
     Unspecified,
 }
 
+// status words with a dedicated error variant, 0x6803 and 0x6808 take
+// precedence over the syscall errors of the same value
+const SW_USER_REJECTED: u16 = 0x6985;
+const SW_USER_REFUSED: u16 = 0x5501;
+const SW_APP_NOT_OPEN: u16 = 0x6e01;
+const SW_WRONG_APP: u16 = 0x6511;
+const SW_BLIND_SIGNING_DISABLED: u16 = 0x6808;
+const SW_DEVICE_LOCKED: u16 = 0x5515;
+const SW_SECURITY_STATUS: u16 = 0x6982;
+const SW_NOT_ENOUGH_MEMORY: u16 = 0x6a84;
+const SW_OVERFLOW: u16 = 0x6803;
+
 #[derive(Error, Debug)]
 pub enum LedgerError {
-    #[error("Unknown error")]
-    Unknown,
+    #[error("Unknown status word {0:#06x}")]
+    Unknown(u16),
 
     #[error("Device panic")]
     DevicePanic,
@@ -27,11 +43,20 @@ pub enum LedgerError {
     #[error("Device not found")]
     DeviceNotFound,
 
-    #[error("Device locked")]
-    DeviceLocked,
+    #[error("Device locked ({0:#06x})")]
+    DeviceLocked(u16),
+
+    #[error("Rejected on the device ({0:#06x})")]
+    UserRejected(u16),
+
+    #[error("IOTA app is not open ({0:#06x})")]
+    AppNotOpen(u16),
 
-    #[error("Syscall error: {0:?}")]
-    Syscall(SyscallError),
+    #[error("Device in use")]
+    DeviceBusy,
+
+    #[error("Syscall error: {0:?} ({1:#06x})")]
+    Syscall(SyscallError, u16),
 
     #[error("APDU error: {0:?}")]
     APDUError(APDUErrorCode),
@@ -47,21 +72,94 @@ pub enum LedgerError {
 
     #[error("Timeout")]
     Timeout,
+
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Invalid mnemonic")]
+    InvalidMnemonic,
+
+    #[error("Invalid version: {0}")]
+    InvalidVersion(String),
+
+    #[error("Invalid derivation path: {0}")]
+    InvalidPath(String),
+
+    #[error("{feature} requires app version {required} or newer, installed is {installed}")]
+    AppVersionTooOld {
+        feature: Feature,
+        required: Version,
+        installed: Version,
+    },
+
+    #[error("Clear signing is not supported by the app")]
+    ClearSigningUnavailable,
+
+    #[error("Clear signing requires the input objects of the transaction")]
+    ObjectsMissing,
+
+    /// Status word if reported by the device, `None` if detected on the host
+    #[error("Blind signing is disabled in the app settings")]
+    BlindSigningDisabled(Option<u16>),
+
+    #[error("Intent {0:?} is not supported by the app")]
+    UnsupportedIntent(IntentScope),
+
+    #[error("Payload of {size} bytes exceeds the maximum of {max} bytes")]
+    PayloadTooLarge { size: usize, max: usize },
+
+    #[error("Payload too large for the device ({0:#06x})")]
+    DevicePayloadTooLarge(u16),
+
+    #[error("Device reported address {reported} but the public key derives {computed}")]
+    AddressMismatch {
+        computed: IotaAddress,
+        reported: IotaAddress,
+    },
+
+    #[error("Signature returned by the device is invalid")]
+    InvalidSignature,
+
+    #[error("Invalid APDU trace: {0}")]
+    InvalidTrace(String),
+
+    #[error("APDU replay mismatch at exchange {index}: expected {expected}, got {actual}")]
+    ReplayMismatch {
+        index: usize,
+        expected: String,
+        actual: String,
+    },
 }
 
 impl LedgerError {
     /// Convert a raw error code to an LedgerError.
     ///
-    /// This method first tries to match standard APDU error codes using
-    /// APDUErrorCode::try_from(). If that fails, it falls back to matching
-    /// legacy/custom error codes that are specific to this application but
-    /// not part of the standard APDU protocol.
+    /// Status words the user can act on (rejection, wrong app, locked device,
+    /// blind signing disabled, payload too large) get dedicated variants
+    /// first. Otherwise this method tries to match standard APDU error codes
+    /// using APDUErrorCode::try_from(). If that fails, it falls back to
+    /// matching legacy/custom error codes that are specific to this
+    /// application but not part of the standard APDU protocol.
     ///
     /// Standard APDU errors (0x6xxx range) will be wrapped in
-    /// APDUError(APDUErrorCode). Custom application errors (like 0x5515 for
-    /// DeviceLocked, 0xe000 for Panic) are handled separately to maintain
-    /// backward compatibility.
+    /// APDUError(APDUErrorCode). Custom application errors (like 0xe000 for
+    /// Panic) are handled separately to maintain backward compatibility.
+    ///
+    /// The IOTA app uses 0x6803 and 0x6808 itself, so they are never reported
+    /// as `Syscall(Overflow, _)` or `Syscall(NotSupported, _)`.
     pub fn get_error(rc: u16) -> Option<LedgerError> {
+        let e = match rc {
+            SW_USER_REJECTED | SW_USER_REFUSED => LedgerError::UserRejected(rc),
+            SW_APP_NOT_OPEN | SW_WRONG_APP => LedgerError::AppNotOpen(rc),
+            SW_BLIND_SIGNING_DISABLED => LedgerError::BlindSigningDisabled(Some(rc)),
+            SW_DEVICE_LOCKED | SW_SECURITY_STATUS => LedgerError::DeviceLocked(rc),
+            SW_NOT_ENOUGH_MEMORY | SW_OVERFLOW => LedgerError::DevicePayloadTooLarge(rc),
+            _ => return Self::get_generic_error(rc),
+        };
+        Some(e)
+    }
+
+    fn get_generic_error(rc: u16) -> Option<LedgerError> {
         // First try to match APDU error codes
         if let Ok(apdu_error) = APDUErrorCode::try_from(rc) {
             match apdu_error {
@@ -73,25 +171,203 @@ impl LedgerError {
         // Fall back to legacy error code matching for non-standard codes
         let e = match rc {
             0xe000 => LedgerError::DevicePanic,
-            0x5515 => LedgerError::DeviceLocked,
             rc if (0x6800..=0x680b).contains(&rc) => {
                 let value = (rc - 0x6800) as u8;
                 let syscall_error = match value {
+                    // 3 and 8 are mapped by `get_error`
                     2 => SyscallError::InvalidParameter,
-                    3 => SyscallError::Overflow,
                     4 => SyscallError::Security,
                     5 => SyscallError::InvalidCrc,
                     6 => SyscallError::InvalidChecksum,
                     7 => SyscallError::InvalidCounter,
-                    8 => SyscallError::NotSupported,
                     9 => SyscallError::InvalidState,
                     10 => SyscallError::Timeout,
                     _ => SyscallError::Unspecified,
                 };
-                LedgerError::Syscall(syscall_error)
+                LedgerError::Syscall(syscall_error, rc)
             }
-            _ => LedgerError::Unknown,
+            _ => LedgerError::Unknown(rc),
         };
         Some(e)
     }
+
+    /// Raw status word of errors reported by the device
+    pub fn status_word(&self) -> Option<u16> {
+        match self {
+            LedgerError::Unknown(sw)
+            | LedgerError::DeviceLocked(sw)
+            | LedgerError::UserRejected(sw)
+            | LedgerError::AppNotOpen(sw)
+            | LedgerError::DevicePayloadTooLarge(sw) => Some(*sw),
+            LedgerError::Syscall(_, sw) => Some(*sw),
+            LedgerError::BlindSigningDisabled(sw) => *sw,
+            LedgerError::APDUError(code) => Some(*code as u16),
+            LedgerError::DevicePanic => Some(0xe000),
+            _ => None,
+        }
+    }
+
+    /// The user declined the request on the device
+    pub fn is_user_rejection(&self) -> bool {
+        matches!(self, LedgerError::UserRejected(_))
+    }
+
+    /// Retrying the same request may succeed, possibly after
+    /// [`requires_user_action`](Self::requires_user_action) was handled
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            LedgerError::DeviceNotFound
+                | LedgerError::DeviceLocked(_)
+                | LedgerError::DeviceBusy
+                | LedgerError::AppNotOpen(_)
+                // the dashboard answers app instructions with this
+                | LedgerError::APDUError(APDUErrorCode::ClaNotSupported)
+                | LedgerError::TransportError
+                | LedgerError::Timeout
+        )
+    }
+
+    /// The user has to do something on the device, e.g. unlock it, open the
+    /// app or change a setting
+    pub fn requires_user_action(&self) -> bool {
+        matches!(
+            self,
+            LedgerError::DeviceNotFound
+                | LedgerError::DeviceLocked(_)
+                | LedgerError::AppNotOpen(_)
+                | LedgerError::APDUError(APDUErrorCode::ClaNotSupported)
+                | LedgerError::BlindSigningDisabled(_)
+                | LedgerError::AppVersionTooOld { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(sw: u16) -> LedgerError {
+        LedgerError::get_error(sw).unwrap()
+    }
+
+    #[test]
+    fn dedicated_variants() {
+        for sw in [SW_USER_REJECTED, SW_USER_REFUSED] {
+            assert!(matches!(error(sw), LedgerError::UserRejected(rc) if rc == sw));
+        }
+        for sw in [SW_APP_NOT_OPEN, SW_WRONG_APP] {
+            assert!(matches!(error(sw), LedgerError::AppNotOpen(rc) if rc == sw));
+        }
+        assert!(matches!(
+            error(SW_BLIND_SIGNING_DISABLED),
+            LedgerError::BlindSigningDisabled(Some(SW_BLIND_SIGNING_DISABLED))
+        ));
+        for sw in [SW_DEVICE_LOCKED, SW_SECURITY_STATUS] {
+            assert!(matches!(error(sw), LedgerError::DeviceLocked(rc) if rc == sw));
+        }
+        for sw in [SW_NOT_ENOUGH_MEMORY, SW_OVERFLOW] {
+            assert!(matches!(error(sw), LedgerError::DevicePayloadTooLarge(rc) if rc == sw));
+        }
+    }
+
+    #[test]
+    fn generic_variants() {
+        assert!(LedgerError::get_error(0x9000).is_none());
+        assert!(matches!(
+            error(0x6e00),
+            LedgerError::APDUError(APDUErrorCode::ClaNotSupported)
+        ));
+        assert!(matches!(
+            error(0x6d00),
+            LedgerError::APDUError(APDUErrorCode::InsNotSupported)
+        ));
+        assert!(matches!(error(0xe000), LedgerError::DevicePanic));
+        assert!(matches!(error(0x1234), LedgerError::Unknown(0x1234)));
+
+        assert!(matches!(
+            error(0x6802),
+            LedgerError::Syscall(SyscallError::InvalidParameter, 0x6802)
+        ));
+        assert!(matches!(
+            error(0x680a),
+            LedgerError::Syscall(SyscallError::Timeout, 0x680a)
+        ));
+        for sw in [0x6800, 0x6801, 0x680b] {
+            assert!(matches!(
+                error(sw),
+                LedgerError::Syscall(SyscallError::Unspecified, rc) if rc == sw
+            ));
+        }
+        for sw in [0x6803, 0x6808] {
+            assert!(!matches!(error(sw), LedgerError::Syscall(..)));
+        }
+    }
+
+    #[test]
+    fn status_word() {
+        for sw in [
+            SW_USER_REJECTED,
+            SW_USER_REFUSED,
+            SW_APP_NOT_OPEN,
+            SW_WRONG_APP,
+            SW_BLIND_SIGNING_DISABLED,
+            SW_DEVICE_LOCKED,
+            SW_SECURITY_STATUS,
+            SW_NOT_ENOUGH_MEMORY,
+            SW_OVERFLOW,
+            0x6800,
+            0x6801,
+            0x6802,
+            0x680b,
+            0x6d00,
+            0x6e00,
+            0xe000,
+            0x1234,
+        ] {
+            assert_eq!(error(sw).status_word(), Some(sw), "{sw:#06x}");
+        }
+        assert_eq!(LedgerError::BlindSigningDisabled(None).status_word(), None);
+        assert_eq!(LedgerError::TransportError.status_word(), None);
+    }
+
+    #[test]
+    fn predicates() {
+        // (status word, user rejection, retryable, requires user action)
+        for (sw, rejection, retryable, user_action) in [
+            (SW_USER_REJECTED, true, false, false),
+            (SW_USER_REFUSED, true, false, false),
+            (SW_APP_NOT_OPEN, false, true, true),
+            (SW_WRONG_APP, false, true, true),
+            (SW_BLIND_SIGNING_DISABLED, false, false, true),
+            (SW_DEVICE_LOCKED, false, true, true),
+            (SW_SECURITY_STATUS, false, true, true),
+            (SW_NOT_ENOUGH_MEMORY, false, false, false),
+            (SW_OVERFLOW, false, false, false),
+            (0x6e00, false, true, true),
+            (0x6802, false, false, false),
+        ] {
+            let e = error(sw);
+            assert_eq!(e.is_user_rejection(), rejection, "{sw:#06x}");
+            assert_eq!(e.is_retryable(), retryable, "{sw:#06x}");
+            assert_eq!(e.requires_user_action(), user_action, "{sw:#06x}");
+        }
+
+        let too_old = LedgerError::AppVersionTooOld {
+            feature: Feature::ClearSigning,
+            required: Version::new(1, 0, 0),
+            installed: Version::new(0, 9, 0),
+        };
+        assert!(too_old.requires_user_action());
+        assert!(!too_old.is_retryable());
+        assert!(LedgerError::BlindSigningDisabled(None).requires_user_action());
+        for e in [
+            LedgerError::ClearSigningUnavailable,
+            LedgerError::ObjectsMissing,
+        ] {
+            assert!(!e.is_user_rejection());
+            assert!(!e.is_retryable());
+            assert!(!e.requires_user_action());
+        }
+    }
 }
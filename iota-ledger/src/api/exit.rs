@@ -8,7 +8,7 @@ use crate::{
 pub fn exec(transport: &Transport) -> Result<(), errors::LedgerError> {
     let cmd = APDUCommand {
         cla: constants::APDU_BOLOS_CLA_B0,
-        ins: constants::APDUInstructionsBolos::AppExitB0 as u8,
+        ins: constants::APDUInstructionsBolos::AppExitB0.ins(),
         p1: 0,
         p2: 0,
         data: Vec::new(),
@@ -0,0 +1,62 @@
+use crate::{
+    Transport,
+    api::{
+        constants, errors,
+        get_version::Version,
+        helpers,
+        packable::{Error as PackableError, Read, Unpackable},
+    },
+    device::DeviceModel,
+    packable_vec,
+};
+
+/// Blind signing is enabled in the app settings
+pub(crate) const FLAG_BLIND_SIGNING: u8 = 1 << 0;
+
+#[derive(Debug)]
+pub struct AppConfig {
+    pub version: Version,
+    /// Raw app flags
+    pub flags: u8,
+    pub device: DeviceModel,
+    /// The app was built in debug mode
+    pub debug: bool,
+}
+
+impl AppConfig {
+    pub fn blind_signing_enabled(&self) -> bool {
+        self.flags & FLAG_BLIND_SIGNING != 0
+    }
+}
+
+impl Unpackable for AppConfig {
+    fn unpack<R: Read>(buf: &mut R) -> Result<Self, PackableError>
+    where
+        Self: Sized,
+    {
+        let version = Version {
+            major: u8::unpack(buf)?,
+            minor: u8::unpack(buf)?,
+            patch: u8::unpack(buf)?,
+        };
+        let flags = u8::unpack(buf)?;
+        let device = DeviceModel::from_app_id(u8::unpack(buf)?);
+        let debug = u8::unpack(buf)? != 0;
+
+        Ok(Self {
+            version,
+            flags,
+            device,
+            debug,
+        })
+    }
+}
+
+pub fn exec(transport: &Transport) -> Result<AppConfig, errors::LedgerError> {
+    helpers::send_with_blocks::<AppConfig>(
+        transport,
+        constants::APDUInstructions::GetAppConfig,
+        packable_vec![()],
+        None,
+    )
+}
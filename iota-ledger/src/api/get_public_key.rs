@@ -11,9 +11,11 @@ use crate::{
         errors, helpers,
         packable::{Error as PackableError, Read, Unpackable},
     },
+    derivation::IotaDerivationPath,
     packable_vec,
 };
 
+#[derive(Clone)]
 pub struct PublicKeyResult {
     pub public_key: Ed25519PublicKey,
     pub address: IotaAddress,
@@ -49,14 +51,25 @@ impl Unpackable for PublicKeyResult {
 
 pub fn exec(
     transport: &Transport,
-    bip32: &bip32::DerivationPath,
+    path: &IotaDerivationPath,
     show: bool,
 ) -> Result<PublicKeyResult, errors::LedgerError> {
-    let payload: helpers::PackedBIP32Path = bip32.into();
+    let payload: helpers::PackedBIP32Path = path.into();
     let ins = if show {
         APDUInstructions::VerifyAddress
     } else {
         APDUInstructions::GetPublicKey
     };
-    helpers::send_with_blocks(transport, ins, packable_vec![payload], None)
+    let result: PublicKeyResult =
+        helpers::send_with_blocks(transport, ins, packable_vec![payload], None)?;
+
+    // don't trust the device with the address derivation
+    let computed = IotaAddress::from(&result.public_key);
+    if computed != result.address {
+        return Err(errors::LedgerError::AddressMismatch {
+            computed,
+            reported: result.address,
+        });
+    }
+    Ok(result)
 }
@@ -1,4 +1,4 @@
-use core::fmt;
+use core::{fmt, str::FromStr};
 
 use crate::{
     Transport,
@@ -8,38 +8,94 @@ use crate::{
     },
 };
 
-#[derive(Debug)]
+/// App version, ordered by semver precedence
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Version {
     pub major: u8,
     pub minor: u8,
     pub patch: u8,
 }
 
+impl Version {
+    pub const fn new(major: u8, minor: u8, patch: u8) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Check the version against a semver requirement such as `>=0.9, <2`
+    pub fn matches(&self, req: &semver::VersionReq) -> bool {
+        req.matches(&semver::Version::from(*self))
+    }
+}
+
+impl From<Version> for semver::Version {
+    fn from(version: Version) -> Self {
+        semver::Version::new(
+            version.major.into(),
+            version.minor.into(),
+            version.patch.into(),
+        )
+    }
+}
+
+impl TryFrom<&semver::Version> for Version {
+    type Error = errors::LedgerError;
+
+    fn try_from(version: &semver::Version) -> Result<Self, Self::Error> {
+        let part = |value: u64| {
+            u8::try_from(value)
+                .map_err(|_| errors::LedgerError::InvalidVersion(version.to_string()))
+        };
+        Ok(Version::new(
+            part(version.major)?,
+            part(version.minor)?,
+            part(version.patch)?,
+        ))
+    }
+}
+
+impl FromStr for Version {
+    type Err = errors::LedgerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let version = semver::Version::parse(s)
+            .map_err(|_| errors::LedgerError::InvalidVersion(s.to_string()))?;
+        Version::try_from(&version)
+    }
+}
+
 impl fmt::Display for Version {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
     }
 }
 
-impl Unpackable for Version {
+/// Response of `GetVersion`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppVersion {
+    pub version: Version,
+    /// Name the app reports for itself, empty on old apps
+    pub app_name: String,
+}
+
+impl Unpackable for AppVersion {
     fn unpack<R: Read>(buf: &mut R) -> Result<Self, PackableError>
     where
         Self: Sized,
     {
-        let major = u8::unpack(buf)?;
-        let minor = u8::unpack(buf)?;
-        let patch = u8::unpack(buf)?;
+        let version = Version::new(u8::unpack(buf)?, u8::unpack(buf)?, u8::unpack(buf)?);
 
-        // consume all extra bytes (app name)
-        while u8::unpack(buf).is_ok() {
-            // NOP
+        // the app name takes up the rest of the response, without length
+        let mut name = Vec::new();
+        while let Ok(b) = u8::unpack(buf) {
+            name.push(b);
         }
+        let app_name = String::from_utf8(name).map_err(|_| PackableError::InvalidUtf8String)?;
 
-        Ok(Self {
-            major,
-            minor,
-            patch,
-        })
+        Ok(Self { version, app_name })
     }
 }
 
@@ -54,8 +110,8 @@ impl Packable for VersionRequest {
     }
 }
 
-pub fn exec(transport: &Transport) -> Result<Version, errors::LedgerError> {
-    helpers::send_with_blocks::<Version>(
+pub fn exec(transport: &Transport) -> Result<AppVersion, errors::LedgerError> {
+    helpers::send_with_blocks::<AppVersion>(
         transport,
         constants::APDUInstructions::GetVersion,
         vec![Box::new(VersionRequest {})],
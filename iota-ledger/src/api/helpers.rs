@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use fastcrypto::hash::{Digest, HashFunction, Sha256};
 use ledger_transport::APDUCommand;
@@ -10,6 +10,8 @@ use crate::{
         errors::{self},
         packable::{Error as PackableError, Packable, PackableObject, Read, Unpackable, Write},
     },
+    derivation::IotaDerivationPath,
+    transport::{BlocksProgress, BlocksStage},
 };
 
 /// Macro to create a vector of boxed packable objects
@@ -37,12 +39,13 @@ impl Packable for PackedBIP32Path {
     }
 }
 
-impl From<&bip32::DerivationPath> for PackedBIP32Path {
-    fn from(path: &bip32::DerivationPath) -> Self {
-        let mut data = Vec::with_capacity(path.len() * 4 + 1);
-        data.push(path.len() as u8);
-        for index in path.iter() {
-            data.extend_from_slice(&index.0.to_le_bytes());
+impl From<&IotaDerivationPath> for PackedBIP32Path {
+    fn from(path: &IotaDerivationPath) -> Self {
+        let indices = path.indices();
+        let mut data = Vec::with_capacity(indices.len() * 4 + 1);
+        data.push(indices.len() as u8);
+        for index in indices {
+            data.extend_from_slice(&index.to_le_bytes());
         }
         PackedBIP32Path { data }
     }
@@ -128,8 +131,7 @@ pub(crate) fn send_with_blocks<R: Unpackable>(
     payloads: Vec<Box<dyn PackableObject>>,
     extra_data: Option<HashMap<Digest<32>, Vec<u8>>>,
 ) -> Result<R, errors::LedgerError> {
-    const CHUNK_SIZE: usize = 180;
-
+    let chunk_size = transport.chunk_size();
     let mut data = extra_data.unwrap_or_default();
     let mut parameter_list: Vec<Digest<32>> = Vec::new();
 
@@ -137,7 +139,7 @@ pub(crate) fn send_with_blocks<R: Unpackable>(
         let packed = payload
             .pack_as_vec()
             .map_err(|_| errors::LedgerError::Serialization)?;
-        let chunks: Vec<&[u8]> = packed.chunks(CHUNK_SIZE).collect();
+        let chunks: Vec<&[u8]> = packed.chunks(chunk_size).collect();
 
         let mut last_hash: Digest<32> = Digest::<32>::new([0u8; 32]);
         for chunk in chunks.iter().rev() {
@@ -167,6 +169,22 @@ fn handle_blocks_protocol<T: Unpackable>(
     mut data: HashMap<Digest<32>, Vec<u8>>,
 ) -> Result<T, errors::LedgerError> {
     let mut result = Vec::new();
+    // the device only asks for confirmation once it has the whole request
+    let confirm = matches!(
+        ins,
+        constants::APDUInstructions::SignTransaction | constants::APDUInstructions::VerifyAddress
+    );
+    let mut progress = BlocksProgress {
+        instruction: ins,
+        stage: BlocksStage::Sending,
+        chunks_served: 0,
+        chunks_total: data.len(),
+        bytes_served: 0,
+        bytes_total: data.values().map(Vec::len).sum(),
+    };
+    // only chunks of the request count, not the ones the device stores with
+    // `PutChunk` and reads back
+    let mut unsent: HashSet<Digest<32>> = data.keys().cloned().collect();
     let ins = ins as u8;
 
     loop {
@@ -187,11 +205,22 @@ fn handle_blocks_protocol<T: Unpackable>(
             }
             LedgerToHost::ResultFinal => {
                 result.extend(rv.payload);
+                progress.stage = BlocksStage::Done;
+                transport.report_progress(&progress);
                 break;
             }
             LedgerToHost::GetChunk => {
                 let key = rv.chunk_hash()?;
                 payload = if let Some(chunk) = data.get(&key) {
+                    if progress.stage == BlocksStage::Sending && unsent.remove(&key) {
+                        progress.chunks_served += 1;
+                        progress.bytes_served += chunk.len();
+                        transport.report_progress(&progress);
+                        if confirm && progress.chunks_served == progress.chunks_total {
+                            progress.stage = BlocksStage::AwaitingConfirmation;
+                            transport.report_progress(&progress);
+                        }
+                    }
                     let mut resp = HostToLedger::GetChunkResponseSuccess.as_vec();
                     resp.extend_from_slice(chunk);
                     resp
@@ -215,7 +244,6 @@ pub(crate) fn exec<T: Unpackable>(
     cmd: APDUCommand<Vec<u8>>,
 ) -> Result<T, errors::LedgerError> {
     transport
-        .transport
         .exchange(&cmd)
         .and_then(|resp| {
             let api_error = errors::LedgerError::get_error(resp.retcode());
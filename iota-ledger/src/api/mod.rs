@@ -5,11 +5,14 @@ pub mod packable;
 pub(crate) mod helpers;
 
 pub(crate) mod exit;
+pub(crate) mod get_app_config;
 pub(crate) mod get_public_key;
 pub(crate) mod get_version;
+pub(crate) mod set_account;
 pub(crate) mod sign_transaction;
 
 // Bolos specific commands
 pub(crate) mod bolos_app_exit;
 pub(crate) mod bolos_app_get_name;
 pub(crate) mod bolos_app_open;
+pub(crate) mod bolos_get_device_info;
@@ -0,0 +1,14 @@
+use crate::{
+    Transport,
+    api::{constants, errors, helpers},
+    packable_vec,
+};
+
+pub fn exec(transport: &Transport, account: u32) -> Result<(), errors::LedgerError> {
+    helpers::send_with_blocks::<()>(
+        transport,
+        constants::APDUInstructions::SetAccount,
+        packable_vec![account],
+        None,
+    )
+}
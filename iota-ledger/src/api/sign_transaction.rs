@@ -5,6 +5,7 @@ use crate::{
         helpers::PackedBIP32Path,
         packable::{Error as PackableError, Packable, Read, Unpackable, Write},
     },
+    derivation::IotaDerivationPath,
     packable_vec,
 };
 
@@ -71,7 +72,7 @@ impl Packable for TransactionObjects {
 
 pub fn exec(
     transport: &Transport,
-    path: &bip32::DerivationPath,
+    path: &IotaDerivationPath,
     transaction: Vec<u8>,
     objects: Vec<Vec<u8>>,
 ) -> Result<SignatureBytes, errors::LedgerError> {
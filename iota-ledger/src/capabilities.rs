@@ -0,0 +1,55 @@
+//! What the connected app is able to do.
+
+use shared_crypto::intent::IntentScope;
+
+use crate::{
+    api::{get_app_config::AppConfig, get_version::Version},
+    config::{Feature, WalletConfig},
+};
+
+/// Default for [`WalletConfig::max_payload_size`], the protocol limit for
+/// transactions
+pub const MAX_PAYLOAD_SIZE: usize = 128 * 1024;
+
+/// Features of the connected app, derived from its version and config
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppCapabilities {
+    pub version: Version,
+    /// The app can show transaction details when given the input objects
+    pub clear_signing: bool,
+    /// Blind signing is enabled, `None` if the app doesn't report its config
+    pub blind_signing: Option<bool>,
+    /// Max. size of a signed payload in bytes
+    pub max_payload_size: usize,
+    /// Intents the app is able to sign
+    pub supported_intents: Vec<IntentScope>,
+}
+
+impl AppCapabilities {
+    /// Features are enabled by the version table in
+    /// [`WalletConfig::minimum_versions`], the blind signing setting is taken
+    /// from the app config.
+    pub fn new(version: Version, app_config: Option<&AppConfig>, config: &WalletConfig) -> Self {
+        let supports = |feature| version >= config.minimum_versions.required(feature);
+        let supported_intents = [
+            (IntentScope::TransactionData, Feature::Signing),
+            (IntentScope::PersonalMessage, Feature::PersonalMessage),
+        ]
+        .into_iter()
+        .filter(|(_, feature)| supports(*feature))
+        .map(|(scope, _)| scope)
+        .collect();
+
+        AppCapabilities {
+            clear_signing: supports(Feature::ClearSigning),
+            blind_signing: app_config.map(AppConfig::blind_signing_enabled),
+            max_payload_size: config.max_payload_size,
+            supported_intents,
+            version,
+        }
+    }
+
+    pub fn supports_intent(&self, scope: IntentScope) -> bool {
+        self.supported_intents.contains(&scope)
+    }
+}
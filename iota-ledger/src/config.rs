@@ -0,0 +1,125 @@
+//! Host side settings of a
+//! [`LedgerHardwareWallet`](crate::LedgerHardwareWallet).
+
+use std::fmt;
+
+use crate::{LedgerError, Version, capabilities::MAX_PAYLOAD_SIZE};
+
+/// Name of the official IOTA app
+pub const DEFAULT_APP_NAME: &str = "IOTA";
+
+/// Whether transactions are signed with the input objects shown on the device
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClearSigningPolicy {
+    /// Clear sign if the app supports it and the input objects are given,
+    /// blind sign otherwise
+    #[default]
+    Prefer,
+    /// Fail with [`LedgerError::ClearSigningUnavailable`](crate::LedgerError::ClearSigningUnavailable)
+    /// if the app can't clear sign, or with
+    /// [`LedgerError::ObjectsMissing`](crate::LedgerError::ObjectsMissing) if a
+    /// transaction is signed without its input objects
+    Require,
+    /// Always blind sign, the objects are not sent to the device
+    Never,
+}
+
+/// Operation gated by [`MinimumVersions`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    Signing,
+    ClearSigning,
+    PersonalMessage,
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Feature::Signing => "signing",
+            Feature::ClearSigning => "clear signing",
+            Feature::PersonalMessage => "personal message signing",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Oldest app version accepted for each [`Feature`]
+///
+/// The [`AppCapabilities`](crate::AppCapabilities) are derived from this
+/// table, `0.0.0` accepts any version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MinimumVersions {
+    pub signing: Version,
+    pub clear_signing: Version,
+    pub personal_message: Version,
+}
+
+impl Default for MinimumVersions {
+    fn default() -> Self {
+        MinimumVersions {
+            signing: Version::new(0, 0, 0),
+            // clear signing was introduced with 1.0.0
+            clear_signing: Version::new(1, 0, 0),
+            personal_message: Version::new(0, 0, 0),
+        }
+    }
+}
+
+impl MinimumVersions {
+    pub fn required(&self, feature: Feature) -> Version {
+        match feature {
+            Feature::Signing => self.signing,
+            Feature::ClearSigning => self.clear_signing,
+            Feature::PersonalMessage => self.personal_message,
+        }
+    }
+
+    /// Fail with [`LedgerError::AppVersionTooOld`] if `installed` is older
+    /// than required for `feature`
+    pub fn check(&self, feature: Feature, installed: Version) -> Result<(), LedgerError> {
+        let required = self.required(feature);
+        if installed < required {
+            return Err(LedgerError::AppVersionTooOld {
+                feature,
+                required,
+                installed,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WalletConfig {
+    /// App opened by
+    /// [`bolos_open_app`](crate::LedgerHardwareWallet::bolos_open_app)
+    pub app_name: String,
+    /// Further names accepted as the IOTA app, e.g. of test builds or forks
+    pub alternative_app_names: Vec<String>,
+    pub clear_signing: ClearSigningPolicy,
+    pub minimum_versions: MinimumVersions,
+    /// Largest payload sent to the device in bytes
+    pub max_payload_size: usize,
+    /// Verify signatures returned by the device before handing them out
+    pub verify_signatures: bool,
+}
+
+impl Default for WalletConfig {
+    fn default() -> Self {
+        WalletConfig {
+            app_name: DEFAULT_APP_NAME.to_string(),
+            alternative_app_names: Vec::new(),
+            clear_signing: ClearSigningPolicy::default(),
+            minimum_versions: MinimumVersions::default(),
+            max_payload_size: MAX_PAYLOAD_SIZE,
+            verify_signatures: true,
+        }
+    }
+}
+
+impl WalletConfig {
+    /// Whether an app reporting `name` is treated as the IOTA app
+    pub fn accepts_app(&self, name: &str) -> bool {
+        self.app_name == name || self.alternative_app_names.iter().any(|n| n == name)
+    }
+}
@@ -0,0 +1,447 @@
+//! IOTA derivation paths and templates for deriving many keys at once.
+
+use std::{fmt, mem, ops::Range, str::FromStr};
+
+use bip32::{ChildNumber, DerivationPath};
+
+use crate::{
+    LedgerError, LedgerHardwareWallet,
+    api::{constants::HARDENED, get_public_key::PublicKeyResult},
+};
+
+/// BIP44 purpose
+pub const PURPOSE: u32 = 44;
+/// Registered SLIP-44 coin type of IOTA
+pub const IOTA_COIN_TYPE: u32 = 4218;
+
+/// A path of the form `m/44'/4218'/account'/change'/index'`
+///
+/// All levels are hardened as required by the app. Besides the full form, with
+/// or without `m/`, the shorthands `account/index` and `account/change/index`
+/// are parsed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IotaDerivationPath {
+    account: u32,
+    change: u32,
+    index: u32,
+}
+
+impl IotaDerivationPath {
+    pub fn new(account: u32, change: u32, index: u32) -> Result<Self, LedgerError> {
+        if let Some(value) = [account, change, index]
+            .into_iter()
+            .find(|value| *value >= HARDENED)
+        {
+            return Err(LedgerError::InvalidPath(format!(
+                "index {value} out of range"
+            )));
+        }
+        Ok(IotaDerivationPath {
+            account,
+            change,
+            index,
+        })
+    }
+
+    pub fn account(&self) -> u32 {
+        self.account
+    }
+
+    pub fn change(&self) -> u32 {
+        self.change
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// All levels including the hardened bit
+    pub fn indices(&self) -> [u32; 5] {
+        [
+            PURPOSE,
+            IOTA_COIN_TYPE,
+            self.account,
+            self.change,
+            self.index,
+        ]
+        .map(|i| i | HARDENED)
+    }
+}
+
+impl TryFrom<&DerivationPath> for IotaDerivationPath {
+    type Error = LedgerError;
+
+    fn try_from(path: &DerivationPath) -> Result<Self, Self::Error> {
+        let invalid = |reason: &str| LedgerError::InvalidPath(format!("{path}: {reason}"));
+
+        let levels: Vec<ChildNumber> = path.iter().collect();
+        let [purpose, coin_type, account, change, index] = levels[..] else {
+            return Err(invalid("expected 5 levels"));
+        };
+        if !levels.iter().all(ChildNumber::is_hardened) {
+            return Err(invalid("all levels must be hardened"));
+        }
+        if purpose.index() != PURPOSE {
+            return Err(invalid("purpose must be 44'"));
+        }
+        if coin_type.index() != IOTA_COIN_TYPE {
+            return Err(invalid("coin type must be 4218'"));
+        }
+        Self::new(account.index(), change.index(), index.index())
+    }
+}
+
+impl TryFrom<DerivationPath> for IotaDerivationPath {
+    type Error = LedgerError;
+
+    fn try_from(path: DerivationPath) -> Result<Self, Self::Error> {
+        Self::try_from(&path)
+    }
+}
+
+impl From<IotaDerivationPath> for DerivationPath {
+    fn from(path: IotaDerivationPath) -> Self {
+        let mut bip32 = DerivationPath::default();
+        bip32.extend(path.indices().map(ChildNumber));
+        bip32
+    }
+}
+
+impl FromStr for IotaDerivationPath {
+    type Err = LedgerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // full path, with or without the `m/` prefix
+        if s.starts_with('m') || s.split('/').count() == 5 {
+            let full = if s.starts_with('m') {
+                s.to_string()
+            } else {
+                format!("m/{s}")
+            };
+            let path: DerivationPath = full
+                .parse()
+                .map_err(|_| LedgerError::InvalidPath(s.to_string()))?;
+            return Self::try_from(&path);
+        }
+
+        // shorthand, levels are hardened with or without `'`
+        let levels = s
+            .split('/')
+            .map(|level| level.strip_suffix('\'').unwrap_or(level).parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| LedgerError::InvalidPath(s.to_string()))?;
+        match levels[..] {
+            [account, index] => Self::new(account, 0, index),
+            [account, change, index] => Self::new(account, change, index),
+            _ => Err(LedgerError::InvalidPath(format!(
+                "{s}: expected account/index or account/change/index"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for IotaDerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "m/{PURPOSE}'/{IOTA_COIN_TYPE}'/{}'/{}'/{}'",
+            self.account, self.change, self.index
+        )
+    }
+}
+
+const DEFAULT_TEMPLATE: &str = "m/44'/4218'/{account}'/0'/{index}'";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Segment {
+    Fixed(ChildNumber),
+    Account { hardened: bool },
+    Index { hardened: bool },
+}
+
+/// A derivation path with `{account}` and `{index}` placeholders, e.g.
+/// `m/44'/4218'/{account}'/0'/{index}'`
+///
+/// Filled in it has to be a valid [`IotaDerivationPath`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathTemplate {
+    segments: Vec<Segment>,
+}
+
+impl PathTemplate {
+    /// Fill in the placeholders
+    pub fn derive(&self, account: u32, index: u32) -> Result<IotaDerivationPath, LedgerError> {
+        let child = |value: u32, hardened: bool| {
+            ChildNumber::new(value, hardened)
+                .map_err(|_| LedgerError::InvalidPath(format!("index {value} out of range")))
+        };
+
+        let mut path = DerivationPath::default();
+        for segment in &self.segments {
+            path.push(match *segment {
+                Segment::Fixed(child_number) => child_number,
+                Segment::Account { hardened } => child(account, hardened)?,
+                Segment::Index { hardened } => child(index, hardened)?,
+            });
+        }
+        IotaDerivationPath::try_from(&path)
+    }
+}
+
+impl Default for PathTemplate {
+    fn default() -> Self {
+        DEFAULT_TEMPLATE.parse().expect("valid default template")
+    }
+}
+
+impl FromStr for PathTemplate {
+    type Err = LedgerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || LedgerError::InvalidPath(s.to_string());
+
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(invalid());
+        }
+
+        let mut segments = Vec::new();
+        for part in parts {
+            let (body, hardened) = match part.strip_suffix('\'') {
+                Some(body) => (body, true),
+                None => (part, false),
+            };
+            let segment = match body {
+                "{account}" => Segment::Account { hardened },
+                "{index}" => Segment::Index { hardened },
+                _ => Segment::Fixed(
+                    ChildNumber::new(body.parse().map_err(|_| invalid())?, hardened)
+                        .map_err(|_| invalid())?,
+                ),
+            };
+            // each placeholder may appear once
+            let placeholder = !matches!(segment, Segment::Fixed(_));
+            if placeholder
+                && segments
+                    .iter()
+                    .any(|s| mem::discriminant(s) == mem::discriminant(&segment))
+            {
+                return Err(invalid());
+            }
+            segments.push(segment);
+        }
+
+        let template = PathTemplate { segments };
+        template.derive(0, 0).map_err(|e| match e {
+            LedgerError::InvalidPath(reason) => LedgerError::InvalidPath(format!("{s} ({reason})")),
+            e => e,
+        })?;
+        Ok(template)
+    }
+}
+
+impl fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+        for segment in &self.segments {
+            let (body, hardened) = match segment {
+                Segment::Fixed(child_number) => {
+                    (child_number.index().to_string(), child_number.is_hardened())
+                }
+                Segment::Account { hardened } => ("{account}".to_string(), *hardened),
+                Segment::Index { hardened } => ("{index}".to_string(), *hardened),
+            };
+            write!(f, "/{body}{}", if hardened { "'" } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+/// Paths searched by [`LedgerHardwareWallet::find_path`]
+#[derive(Clone, Debug)]
+pub struct SearchBounds {
+    pub template: PathTemplate,
+    pub accounts: Range<u32>,
+    pub indices: Range<u32>,
+}
+
+impl Default for SearchBounds {
+    fn default() -> Self {
+        SearchBounds {
+            template: PathTemplate::default(),
+            accounts: 0..10,
+            indices: 0..20,
+        }
+    }
+}
+
+/// A key derived by [`LedgerHardwareWallet::derive_public_keys`]
+#[derive(Clone)]
+pub struct DerivedKey {
+    pub account: u32,
+    pub index: u32,
+    pub path: IotaDerivationPath,
+    pub public_key: PublicKeyResult,
+}
+
+/// Lazily derives the keys of all account/index combinations, accounts first
+pub struct DerivedKeys<'a> {
+    wallet: &'a LedgerHardwareWallet,
+    template: PathTemplate,
+    accounts: Range<u32>,
+    indices: Range<u32>,
+    position: u64,
+    total: u64,
+}
+
+impl<'a> DerivedKeys<'a> {
+    pub(crate) fn new(
+        wallet: &'a LedgerHardwareWallet,
+        template: PathTemplate,
+        accounts: Range<u32>,
+        indices: Range<u32>,
+    ) -> Self {
+        let total = accounts.len() as u64 * indices.len() as u64;
+        DerivedKeys {
+            wallet,
+            template,
+            accounts,
+            indices,
+            position: 0,
+            total,
+        }
+    }
+
+    /// Number of keys derived so far
+    pub fn completed(&self) -> u64 {
+        self.position
+    }
+
+    /// Number of keys in total
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+impl Iterator for DerivedKeys<'_> {
+    type Item = Result<DerivedKey, LedgerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.total {
+            return None;
+        }
+        let per_account = self.indices.len() as u64;
+        let account = self.accounts.start + (self.position / per_account) as u32;
+        let index = self.indices.start + (self.position % per_account) as u32;
+        self.position += 1;
+
+        Some(self.template.derive(account, index).and_then(|path| {
+            let public_key = self.wallet.get_public_key(&path)?;
+            Ok(DerivedKey {
+                account,
+                index,
+                path,
+                public_key,
+            })
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.total - self.position) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for DerivedKeys<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(s: &str) -> Result<IotaDerivationPath, LedgerError> {
+        s.parse()
+    }
+
+    #[test]
+    fn parse_full_path() {
+        let expected = IotaDerivationPath::new(1, 2, 3).unwrap();
+        assert_eq!(path("m/44'/4218'/1'/2'/3'").unwrap(), expected);
+        assert_eq!(path("44'/4218'/1'/2'/3'").unwrap(), expected);
+        assert_eq!(expected.to_string(), "m/44'/4218'/1'/2'/3'");
+    }
+
+    #[test]
+    fn parse_shorthand() {
+        assert_eq!(
+            path("3/7").unwrap(),
+            IotaDerivationPath::new(3, 0, 7).unwrap()
+        );
+        assert_eq!(
+            path("1'/2'/3'").unwrap(),
+            IotaDerivationPath::new(1, 2, 3).unwrap()
+        );
+    }
+
+    #[test]
+    fn reject_invalid_paths() {
+        for invalid in [
+            // non-hardened levels
+            "m/44'/4218'/0'/0'/0",
+            "44/4218'/0'/0'/0'",
+            // wrong coin type or purpose
+            "m/44'/1'/0'/0'/0'",
+            "44'/1'/0'/0'/0'",
+            "m/49'/4218'/0'/0'/0'",
+            // wrong number of levels
+            "m/44'/4218'/0'/0'",
+            "5",
+            "1/2/3/4",
+            // out of range
+            "2147483648/0",
+            "",
+        ] {
+            assert!(path(invalid).is_err(), "{invalid} should be rejected");
+        }
+    }
+
+    #[test]
+    fn template_default() {
+        let template = PathTemplate::default();
+        assert_eq!(template.to_string(), DEFAULT_TEMPLATE);
+        assert_eq!(
+            template.derive(2, 7).unwrap(),
+            IotaDerivationPath::new(2, 0, 7).unwrap()
+        );
+    }
+
+    #[test]
+    fn template_parse() {
+        let template: PathTemplate = "m/44'/4218'/0'/{account}'/{index}'".parse().unwrap();
+        assert_eq!(template.to_string(), "m/44'/4218'/0'/{account}'/{index}'");
+        assert_eq!(
+            template.derive(1, 5).unwrap(),
+            IotaDerivationPath::new(0, 1, 5).unwrap()
+        );
+    }
+
+    #[test]
+    fn template_reject_invalid() {
+        for invalid in [
+            // duplicate placeholders
+            "m/44'/4218'/{index}'/0'/{index}'",
+            "m/44'/4218'/{account}'/{account}'/{index}'",
+            // non-hardened placeholder
+            "m/44'/4218'/{account}'/0'/{index}",
+            // wrong coin type
+            "m/44'/1'/{account}'/0'/{index}'",
+            // missing `m`
+            "44'/4218'/{account}'/0'/{index}'",
+            "m/44'/4218'/{account}'/0'/{idx}'",
+        ] {
+            assert!(
+                invalid.parse::<PathTemplate>().is_err(),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+}
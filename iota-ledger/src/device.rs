@@ -0,0 +1,131 @@
+use std::fmt;
+
+use crate::transport::{DEFAULT_CHUNK_SIZE, MAX_CHUNK_SIZE};
+
+/// Ledger device models
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceModel {
+    NanoS,
+    NanoX,
+    NanoSPlus,
+    Stax,
+    Flex,
+    Unknown,
+}
+
+impl DeviceModel {
+    /// Infer the model from the USB product id
+    ///
+    /// Older firmwares use a fixed product id per model, newer ones encode the
+    /// model in the upper byte.
+    pub fn from_product_id(product_id: u16) -> Self {
+        match product_id {
+            0x0001 => DeviceModel::NanoS,
+            0x0004 => DeviceModel::NanoX,
+            0x0005 => DeviceModel::NanoSPlus,
+            0x0006 => DeviceModel::Stax,
+            0x0007 => DeviceModel::Flex,
+            _ => match product_id >> 8 {
+                0x10 => DeviceModel::NanoS,
+                0x40 => DeviceModel::NanoX,
+                0x50 => DeviceModel::NanoSPlus,
+                0x60 => DeviceModel::Stax,
+                0x70 => DeviceModel::Flex,
+                _ => DeviceModel::Unknown,
+            },
+        }
+    }
+
+    /// Infer the model from the target id reported by the dashboard
+    pub fn from_target_id(target_id: u32) -> Self {
+        match target_id & 0xffff_0000 {
+            0x3110_0000 => DeviceModel::NanoS,
+            0x3300_0000 => DeviceModel::NanoX,
+            0x3310_0000 => DeviceModel::NanoSPlus,
+            0x3320_0000 => DeviceModel::Stax,
+            0x3330_0000 => DeviceModel::Flex,
+            _ => DeviceModel::Unknown,
+        }
+    }
+
+    pub(crate) fn target_id(self) -> u32 {
+        match self {
+            DeviceModel::NanoS => 0x3110_0004,
+            DeviceModel::NanoX => 0x3300_0004,
+            DeviceModel::NanoSPlus => 0x3310_0004,
+            DeviceModel::Stax => 0x3320_0004,
+            DeviceModel::Flex => 0x3330_0004,
+            DeviceModel::Unknown => 0,
+        }
+    }
+
+    /// Largest blocks protocol chunk the model handles
+    ///
+    /// The Nano S has little RAM, unknown models get the conservative default.
+    pub fn max_chunk_size(self) -> usize {
+        match self {
+            DeviceModel::NanoS | DeviceModel::Unknown => DEFAULT_CHUNK_SIZE,
+            _ => MAX_CHUNK_SIZE,
+        }
+    }
+
+    /// The IOTA app is available for this model
+    ///
+    /// The Nano S is no longer supported by current app releases.
+    pub fn is_supported(self) -> bool {
+        !matches!(self, DeviceModel::NanoS | DeviceModel::Unknown)
+    }
+
+    // device byte of the app config
+    pub(crate) fn from_app_id(id: u8) -> Self {
+        match id {
+            0 => DeviceModel::NanoS,
+            1 => DeviceModel::NanoX,
+            2 => DeviceModel::NanoSPlus,
+            3 => DeviceModel::Stax,
+            4 => DeviceModel::Flex,
+            _ => DeviceModel::Unknown,
+        }
+    }
+
+    pub(crate) fn app_id(self) -> u8 {
+        match self {
+            DeviceModel::NanoS => 0,
+            DeviceModel::NanoX => 1,
+            DeviceModel::NanoSPlus => 2,
+            DeviceModel::Stax => 3,
+            DeviceModel::Flex => 4,
+            DeviceModel::Unknown => 0xff,
+        }
+    }
+}
+
+impl fmt::Display for DeviceModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DeviceModel::NanoS => "Nano S",
+            DeviceModel::NanoX => "Nano X",
+            DeviceModel::NanoSPlus => "Nano S Plus",
+            DeviceModel::Stax => "Stax",
+            DeviceModel::Flex => "Flex",
+            DeviceModel::Unknown => "Unknown",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Device and firmware details, see
+/// [`LedgerHardwareWallet::get_device_info`](crate::LedgerHardwareWallet::get_device_info)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Model from the target id, or the USB product id if it is unknown
+    pub model: DeviceModel,
+    pub target_id: u32,
+    /// Secure element (BOLOS) version
+    pub se_version: String,
+    pub mcu_version: String,
+    /// Raw device flags
+    pub flags: Vec<u8>,
+    /// USB product id if connected via HID
+    pub product_id: Option<u16>,
+}
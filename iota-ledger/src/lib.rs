@@ -1,35 +1,69 @@
 //! Library
 
-use std::vec;
+use std::{ops::Range, vec};
 
-use log::debug;
-pub use transport::{LedgerTransport, Transport, TransportTypes, create_transport};
+use log::{debug, warn};
+pub use transport::{
+    ApduObserver, LedgerTransport, RecordingTransport, ReplayTransport, TcpConfig, Transport,
+    TransportOptions, TransportTypes, create_transport, create_transport_with_options,
+};
 
-pub use crate::api::errors::LedgerError;
-use crate::api::{
-    get_public_key, get_public_key::PublicKeyResult, get_version::Version, sign_transaction,
+pub use crate::{
+    api::{
+        bolos_app_get_name::Response as RunningApp,
+        errors::LedgerError,
+        get_version::{AppVersion, Version},
+    },
+    capabilities::AppCapabilities,
+    config::{ClearSigningPolicy, Feature, MinimumVersions, WalletConfig},
+    derivation::{DerivedKey, DerivedKeys, IotaDerivationPath, PathTemplate, SearchBounds},
+    lifecycle::AppOpenProgress,
+};
+use crate::{
+    api::{
+        errors::APDUErrorCode, get_app_config, get_app_config::AppConfig, get_public_key,
+        get_public_key::PublicKeyResult, set_account, sign_transaction,
+    },
+    device::{DeviceInfo, DeviceModel},
+    session::Session,
 };
 pub mod api;
+pub mod capabilities;
+pub mod config;
+pub mod derivation;
+pub mod device;
+mod lifecycle;
+mod session;
 
 pub mod transport;
 use iota_types::{
     base_types::IotaAddress,
-    crypto::{Ed25519IotaSignature, SignatureScheme, ToFromBytes},
+    crypto::{Ed25519IotaSignature, IotaSignature, SignatureScheme, ToFromBytes},
     object::Object,
 };
 use serde::Serialize;
-use shared_crypto::intent::IntentMessage;
+use shared_crypto::intent::{IntentMessage, IntentScope};
 
 /// Get Ledger by transport_type
 pub fn get_ledger_by_type(
     transport_type: TransportTypes,
 ) -> Result<LedgerHardwareWallet, LedgerError> {
-    let transport = create_transport(transport_type, None)?;
+    get_ledger_with_options(transport_type, &TransportOptions::default())
+}
+
+/// Get Ledger by transport_type, see [`TransportOptions`] for the options
+pub fn get_ledger_with_options(
+    transport_type: TransportTypes,
+    options: &TransportOptions,
+) -> Result<LedgerHardwareWallet, LedgerError> {
+    let transport = create_transport_with_options(transport_type, options)?;
     Ok(crate::LedgerHardwareWallet::new(transport))
 }
 
 pub struct LedgerHardwareWallet {
     transport: Transport,
+    config: WalletConfig,
+    session: Session,
 }
 
 pub struct SignedTransaction<T> {
@@ -39,21 +73,87 @@ pub struct SignedTransaction<T> {
 }
 
 impl LedgerHardwareWallet {
-    fn new(transport: Transport) -> Self {
-        LedgerHardwareWallet { transport }
+    pub fn new(mut transport: Transport) -> Self {
+        let session = Session::default();
+        transport.add_observer(session.clone());
+        LedgerHardwareWallet {
+            transport,
+            config: WalletConfig::default(),
+            session,
+        }
     }
 
-    /// Get currently opened app
-    /// If "BOLOS" is returned, the dashboard is open
+    pub fn with_config(mut self, config: WalletConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &WalletConfig {
+        &self.config
+    }
+
+    /// Register an observer on the transport, e.g. to follow the
+    /// [`BlocksProgress`](transport::BlocksProgress) of large transactions
+    pub fn add_observer(&mut self, observer: impl ApduObserver + 'static) {
+        self.transport.add_observer(observer);
+    }
+
+    /// Create a wallet on top of any [`LedgerTransport`] implementation
+    pub fn with_transport(transport: impl LedgerTransport + 'static) -> Self {
+        Self::new(Transport::new(transport))
+    }
+
+    /// Name, version and OS flags of the running app
+    /// `BOLOS` is reported if the dashboard is open
+    pub fn get_running_app(&self) -> Result<RunningApp, LedgerError> {
+        crate::api::bolos_app_get_name::exec(&self.transport)
+    }
+
+    /// Check if one of the configured apps is open, see
+    /// [`WalletConfig::accepts_app`]
     pub fn is_app_open(&self) -> Result<bool, LedgerError> {
-        let app = crate::api::bolos_app_get_name::exec(&self.transport)?;
-        Ok(app.app == "IOTA")
+        let app = self.get_running_app()?;
+        let open = self.config.accepts_app(&app.app);
+        if !open {
+            self.session.clear();
+        }
+        Ok(open)
     }
 
-    /// Open app on the nano s/x
+    /// Open the configured app on the nano s/x
     /// Only works if dashboard is open
     pub fn bolos_open_app(&self) -> Result<(), LedgerError> {
-        crate::api::bolos_app_open::exec(&self.transport, "IOTA".to_string())
+        crate::api::bolos_app_open::exec(&self.transport, self.config.app_name.clone())
+    }
+
+    /// Model and firmware versions of the device
+    /// Only works if dashboard is open
+    pub fn get_device_info(&self) -> Result<DeviceInfo, LedgerError> {
+        let info = crate::api::bolos_get_device_info::exec(&self.transport)?;
+        let product_id = self.transport.device().map(|device| device.product_id);
+
+        let model = match DeviceModel::from_target_id(info.target_id) {
+            DeviceModel::Unknown => {
+                product_id.map_or(DeviceModel::Unknown, DeviceModel::from_product_id)
+            }
+            model => model,
+        };
+        self.transport.negotiate_chunk_size(model);
+        if !model.is_supported() {
+            warn!(
+                "{model} (target id {:#010x}) is not supported by the IOTA app",
+                info.target_id
+            );
+        }
+
+        Ok(DeviceInfo {
+            model,
+            target_id: info.target_id,
+            se_version: info.se_version,
+            mcu_version: info.mcu_version,
+            flags: info.flags,
+            product_id,
+        })
     }
 
     /// Close current opened app on the nano s/x
@@ -66,67 +166,250 @@ impl LedgerHardwareWallet {
         &self.transport
     }
 
+    /// Forget the cached version, capabilities and public keys
+    ///
+    /// Happens automatically on transport errors, unexpected status words and
+    /// when an app is opened or closed.
+    pub fn invalidate_session(&self) {
+        self.session.clear();
+    }
+
     pub fn get_version(&self) -> Result<Version, LedgerError> {
-        let version = crate::api::get_version::exec(&self.transport)?;
+        self.get_app_version().map(|app| app.version)
+    }
+
+    /// Version and name reported by the app
+    pub fn get_app_version(&self) -> Result<AppVersion, LedgerError> {
+        if let Some(app) = self.session.with(|cache| cache.version.clone()) {
+            return Ok(app);
+        }
+        let app = crate::api::get_version::exec(&self.transport)?;
+        self.session.with(|cache| cache.version = Some(app.clone()));
 
         debug!(
-            "Connected Ledger app version: {}.{}.{}",
-            version.major, version.minor, version.patch
+            "Connected Ledger app version: {} {}",
+            app.app_name, app.version
         );
 
-        Ok(version)
+        Ok(app)
+    }
+
+    /// App version, flags and the device model as reported by the app
+    pub fn get_app_config(&self) -> Result<AppConfig, LedgerError> {
+        get_app_config::exec(&self.transport)
+    }
+
+    /// Capabilities of the connected app, see [`AppCapabilities`]
+    pub fn get_capabilities(&self) -> Result<AppCapabilities, LedgerError> {
+        if let Some(capabilities) = self.session.with(|cache| cache.capabilities.clone()) {
+            return Ok(capabilities);
+        }
+        let version = self.get_version()?;
+        let config = match self.get_app_config() {
+            Ok(config) => {
+                self.transport.negotiate_chunk_size(config.device);
+                Some(config)
+            }
+            // older apps don't implement GetAppConfig
+            Err(LedgerError::APDUError(APDUErrorCode::InsNotSupported)) => None,
+            Err(e) => return Err(e),
+        };
+        let capabilities = AppCapabilities::new(version, config.as_ref(), &self.config);
+        self.session
+            .with(|cache| cache.capabilities = Some(capabilities.clone()));
+        Ok(capabilities)
+    }
+
+    /// Select the active account on apps that support it
+    pub fn set_account(&self, account: u32) -> Result<(), LedgerError> {
+        set_account::exec(&self.transport, account)
     }
 
     pub fn verify_address(
         &self,
-        bip32: &bip32::DerivationPath,
+        path: &IotaDerivationPath,
     ) -> Result<PublicKeyResult, LedgerError> {
-        get_public_key::exec(&self.transport, bip32, true)
+        get_public_key::exec(&self.transport, path, true)
     }
 
     pub fn get_public_key(
         &self,
-        bip32: &bip32::DerivationPath,
+        path: &IotaDerivationPath,
     ) -> Result<PublicKeyResult, LedgerError> {
-        get_public_key::exec(&self.transport, bip32, false)
+        if let Some(public_key) = self
+            .session
+            .with(|cache| cache.public_keys.get(path).cloned())
+        {
+            return Ok(public_key);
+        }
+        let public_key = get_public_key::exec(&self.transport, path, false)?;
+        self.session
+            .with(|cache| cache.public_keys.insert(*path, public_key.clone()));
+        Ok(public_key)
+    }
+
+    /// Public keys of all `accounts` x `indices` combinations of `template`
+    ///
+    /// Keys are queried lazily while iterating and cached per path.
+    pub fn derive_public_keys(
+        &self,
+        template: &PathTemplate,
+        accounts: Range<u32>,
+        indices: Range<u32>,
+    ) -> DerivedKeys<'_> {
+        DerivedKeys::new(self, template.clone(), accounts, indices)
+    }
+
+    /// Search the path within `bounds` that derives `address`
+    ///
+    /// Found paths are cached for the session.
+    pub fn find_path(
+        &self,
+        address: &IotaAddress,
+        bounds: &SearchBounds,
+    ) -> Result<Option<IotaDerivationPath>, LedgerError> {
+        if let Some(path) = self.session.with(|cache| cache.paths.get(address).cloned()) {
+            return Ok(Some(path));
+        }
+
+        let keys = self.derive_public_keys(
+            &bounds.template,
+            bounds.accounts.clone(),
+            bounds.indices.clone(),
+        );
+        for key in keys {
+            let key = key?;
+            if key.public_key.address == *address {
+                debug!("found {address} at {}", key.path);
+                self.session
+                    .with(|cache| cache.paths.insert(*address, key.path));
+                return Ok(Some(key.path));
+            }
+        }
+        Ok(None)
     }
 
     pub fn get_signature_scheme(&self) -> SignatureScheme {
         SignatureScheme::ED25519
     }
 
+    // whether to clear sign, or why the app can't sign the intent
+    fn check_intent(
+        &self,
+        capabilities: &AppCapabilities,
+        scope: IntentScope,
+        size: usize,
+        has_objects: bool,
+    ) -> Result<bool, LedgerError> {
+        let minimum = &self.config.minimum_versions;
+        if !capabilities.supports_intent(scope) {
+            let feature = match scope {
+                IntentScope::PersonalMessage => Feature::PersonalMessage,
+                _ => Feature::Signing,
+            };
+            // name the required version if the app is just too old
+            minimum.check(feature, capabilities.version)?;
+            return Err(LedgerError::UnsupportedIntent(scope));
+        }
+
+        if size > capabilities.max_payload_size {
+            return Err(LedgerError::PayloadTooLarge {
+                size,
+                max: capabilities.max_payload_size,
+            });
+        }
+
+        // without the input objects the app can only blind sign a transaction
+        let objects_missing = scope == IntentScope::TransactionData && !has_objects;
+        let clear_sign = match (self.config.clear_signing, capabilities.clear_signing) {
+            (ClearSigningPolicy::Never, _) => false,
+            (_, true) if !objects_missing => true,
+            (ClearSigningPolicy::Require, true) => return Err(LedgerError::ObjectsMissing),
+            (ClearSigningPolicy::Prefer, true) => {
+                warn!("no input objects given, blind signing instead");
+                false
+            }
+            (ClearSigningPolicy::Require, false) => {
+                minimum.check(Feature::ClearSigning, capabilities.version)?;
+                return Err(LedgerError::ClearSigningUnavailable);
+            }
+            (ClearSigningPolicy::Prefer, false) => {
+                warn!(
+                    "app version {} doesn't support clear signing, blind signing instead",
+                    capabilities.version
+                );
+                false
+            }
+        };
+        if !clear_sign
+            && scope == IntentScope::TransactionData
+            && capabilities.blind_signing == Some(false)
+        {
+            return Err(LedgerError::BlindSigningDisabled(None));
+        }
+        Ok(clear_sign)
+    }
+
     pub fn sign_intent<T: Serialize>(
         &self,
-        bip32: &bip32::DerivationPath,
+        path: &IotaDerivationPath,
         intent_msg: IntentMessage<T>,
         objects: Vec<Object>,
     ) -> Result<SignedTransaction<T>, LedgerError> {
-        let version = self.get_version()?;
-        let public_key = self.get_public_key(bip32)?;
+        let capabilities = self.get_capabilities()?;
         let intent_bytes = bcs::to_bytes(&intent_msg).map_err(|_| LedgerError::Serialization)?;
+        // nothing is sent if the checks fail, so the session observer can't
+        // notice the user changing the app settings or updating the app.
+        // Query them again on the next attempt, the keys stay valid.
+        let clear_sign = self
+            .check_intent(
+                &capabilities,
+                intent_msg.intent.scope,
+                intent_bytes.len(),
+                !objects.is_empty(),
+            )
+            .inspect_err(|_| {
+                self.session.with(|cache| {
+                    cache.version = None;
+                    cache.capabilities = None;
+                })
+            })?;
 
-        let signature = (if version.major > 0 {
-            let bcs_objects: Vec<Vec<u8>> = objects
+        let bcs_objects: Vec<Vec<u8>> = if clear_sign {
+            objects
                 .iter()
                 .map(|o| bcs::to_bytes(&o).map_err(|_| LedgerError::Serialization))
-                .collect::<Result<_, _>>()?;
-            // If the major version is greater than 0, we assume it supports clear signing
-            sign_transaction::exec(self.transport(), bip32, intent_bytes, bcs_objects)
+                .collect::<Result<_, _>>()?
         } else {
-            sign_transaction::exec(self.transport(), bip32, intent_bytes, vec![])
-        })?;
+            vec![]
+        };
+
+        let public_key = self.get_public_key(path)?;
+        let signature = sign_transaction::exec(self.transport(), path, intent_bytes, bcs_objects)?;
 
         let mut signature_bytes: Vec<u8> = Vec::new();
         signature_bytes.extend_from_slice(&[self.get_signature_scheme().flag()]);
         signature_bytes.extend_from_slice(&signature.bytes);
         signature_bytes.extend_from_slice(public_key.public_key.as_ref());
 
+        let signature = Ed25519IotaSignature::from_bytes(&signature_bytes)
+            .map_err(|_| LedgerError::Serialization)?;
+        let address =
+            IotaAddress::from_bytes(public_key.address).map_err(|_| LedgerError::Serialization)?;
+
+        if self.config.verify_signatures {
+            signature
+                .verify_secure(&intent_msg, address, self.get_signature_scheme())
+                .map_err(|e| {
+                    debug!("signature verification failed: {e}");
+                    LedgerError::InvalidSignature
+                })?;
+        }
+
         Ok(SignedTransaction {
             intent_msg,
-            signature: Ed25519IotaSignature::from_bytes(&signature_bytes)
-                .map_err(|_| LedgerError::Serialization)?,
-            address: IotaAddress::from_bytes(public_key.address)
-                .map_err(|_| LedgerError::Serialization)?,
+            signature,
+            address,
         })
     }
 }
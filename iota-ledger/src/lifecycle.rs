@@ -0,0 +1,107 @@
+//! Bringing the device into a state where the IOTA app is running.
+
+use std::{
+    fmt, thread,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+
+use crate::{LedgerError, LedgerHardwareWallet, api::bolos_app_get_name};
+
+// name reported by the dashboard
+const DASHBOARD: &str = "BOLOS";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Steps reported by [`LedgerHardwareWallet::ensure_app_open`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AppOpenProgress {
+    /// The device is locked, waiting for the PIN to be entered
+    WaitingForUnlock,
+    /// Another app is open and gets closed
+    ExitingApp(String),
+    /// Opening the app has to be confirmed on the device
+    ConfirmAppOpen,
+    /// Waiting for the app to start
+    WaitingForApp,
+    /// The app is running
+    Open,
+}
+
+impl fmt::Display for AppOpenProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppOpenProgress::WaitingForUnlock => f.write_str("unlock your device"),
+            AppOpenProgress::ExitingApp(app) => write!(f, "closing {app}"),
+            AppOpenProgress::ConfirmAppOpen => {
+                f.write_str("confirm opening the app on your device")
+            }
+            AppOpenProgress::WaitingForApp => f.write_str("waiting for the app to start"),
+            AppOpenProgress::Open => f.write_str("app is open"),
+        }
+    }
+}
+
+impl LedgerHardwareWallet {
+    /// Make sure the IOTA app is running, opening it if necessary
+    ///
+    /// Closes any other open app, asks to open the configured app and polls
+    /// until an accepted app runs, see [`WalletConfig`](crate::WalletConfig).
+    /// Each step is reported once to `progress`. Fails with
+    /// [`LedgerError::Timeout`] if the app isn't running after `timeout`.
+    pub fn ensure_app_open(
+        &self,
+        timeout: Duration,
+        mut progress: impl FnMut(AppOpenProgress),
+    ) -> Result<(), LedgerError> {
+        let deadline = Instant::now() + timeout;
+        let mut last = None;
+        let mut report = |state: AppOpenProgress| {
+            if last.as_ref() != Some(&state) {
+                debug!("ensure_app_open: {state}");
+                last = Some(state.clone());
+                progress(state);
+            }
+        };
+        let mut open_requested = false;
+
+        loop {
+            match bolos_app_get_name::exec(&self.transport) {
+                Ok(app) if self.config.accepts_app(&app.app) => {
+                    report(AppOpenProgress::Open);
+                    return Ok(());
+                }
+                Ok(app) if app.app == DASHBOARD => {
+                    if open_requested {
+                        report(AppOpenProgress::WaitingForApp);
+                    } else {
+                        report(AppOpenProgress::ConfirmAppOpen);
+                        match self.bolos_open_app() {
+                            Ok(()) => open_requested = true,
+                            Err(LedgerError::DeviceLocked(_)) => {
+                                report(AppOpenProgress::WaitingForUnlock)
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                }
+                Ok(app) => {
+                    report(AppOpenProgress::ExitingApp(app.app));
+                    self.bolos_exit_app()?;
+                    open_requested = false;
+                }
+                Err(LedgerError::DeviceLocked(_)) => report(AppOpenProgress::WaitingForUnlock),
+                // the device may briefly disappear while switching apps
+                Err(e) if e.is_retryable() => debug!("ensure_app_open: {e}"),
+                Err(e) => return Err(e),
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(LedgerError::Timeout);
+            }
+            thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+}
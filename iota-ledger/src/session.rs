@@ -0,0 +1,76 @@
+//! Cache of app state that is costly to query on every call.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use iota_types::base_types::IotaAddress;
+use log::debug;
+
+use crate::{
+    AppCapabilities, LedgerError,
+    api::{
+        constants::{APDUInstructions, APDUInstructionsBolos},
+        errors::APDUErrorCode,
+        get_public_key::PublicKeyResult,
+        get_version::AppVersion,
+    },
+    derivation::IotaDerivationPath,
+    transport::{ApduEvent, ApduObserver, Instruction},
+};
+
+#[derive(Default)]
+pub(crate) struct SessionCache {
+    pub(crate) version: Option<AppVersion>,
+    pub(crate) capabilities: Option<AppCapabilities>,
+    pub(crate) public_keys: HashMap<IotaDerivationPath, PublicKeyResult>,
+    // paths found by `find_path`
+    pub(crate) paths: HashMap<IotaAddress, IotaDerivationPath>,
+}
+
+/// Shared handle to the cache, registered as observer on the transport so it
+/// is dropped whenever the device state may have changed.
+#[derive(Clone, Default)]
+pub(crate) struct Session(Arc<Mutex<SessionCache>>);
+
+impl Session {
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut SessionCache) -> R) -> R {
+        f(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    pub(crate) fn clear(&self) {
+        self.with(|cache| *cache = SessionCache::default());
+    }
+}
+
+impl ApduObserver for Session {
+    fn after_exchange(&self, event: &ApduEvent<'_>) {
+        let keep = match event.status_word() {
+            // opening or closing apps changes what is on the other end
+            Some(sw) if sw == APDUErrorCode::NoError as u16 => !matches!(
+                event.instruction,
+                Instruction::App(APDUInstructions::Exit)
+                    | Instruction::Bolos(
+                        APDUInstructionsBolos::AppExitB0 | APDUInstructionsBolos::OpenAppE0
+                    )
+            ),
+            // the app doesn't implement the instruction, e.g. `GetAppConfig`
+            // on older versions, nothing changed
+            Some(sw)
+                if sw == APDUErrorCode::InsNotSupported as u16
+                    && matches!(event.instruction, Instruction::App(_)) =>
+            {
+                true
+            }
+            // rejected by the user, nothing changed
+            Some(sw) => LedgerError::get_error(sw).is_some_and(|e| e.is_user_rejection()),
+            // transport failure, the device may be gone
+            None => false,
+        };
+        if !keep {
+            debug!("invalidating session cache after {:?}", event.instruction);
+            self.clear();
+        }
+    }
+}
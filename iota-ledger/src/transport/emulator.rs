@@ -0,0 +1,479 @@
+//! In-process emulation of the IOTA Ledger app.
+//!
+//! [`TransportEmulator`] answers APDUs like the IOTA app (and the BOLOS
+//! dashboard) would, including the blocks protocol, so the whole stack can be
+//! exercised without a device or Speculos.
+
+use std::sync::{Mutex, PoisonError};
+
+use fastcrypto::{
+    ed25519::{Ed25519KeyPair, Ed25519PrivateKey},
+    hash::{Blake2b256, HashFunction, Sha256},
+    traits::{KeyPair, Signer, ToFromBytes},
+};
+use hmac::{Hmac, Mac};
+use iota_types::base_types::IotaAddress;
+use ledger_transport::{APDUAnswer, APDUCommand};
+
+use crate::{
+    LedgerError,
+    api::{
+        constants::{
+            APDU_BOLOS_CLA_B0, APDU_BOLOS_CLA_E0, APDU_CLA, APDUInstructions,
+            APDUInstructionsBolos, HARDENED,
+        },
+        get_app_config::FLAG_BLIND_SIGNING,
+    },
+    config::DEFAULT_APP_NAME,
+    device::DeviceModel,
+    transport::LedgerTransport,
+};
+
+/// Mnemonic Speculos uses by default, so the emulator derives the same keys
+pub const DEFAULT_MNEMONIC: &str = "glory promote mansion idle axis finger extra february uncover one trip resource lawn turtle enact monster seven myth punch hobby comfort wild raise skin";
+
+const SW_OK: u16 = 0x9000;
+const SW_WRONG_LENGTH: u16 = 0x6700;
+const SW_DATA_INVALID: u16 = 0x6984;
+const SW_BAD_DATA: u16 = 0x6a80;
+const SW_INS_NOT_SUPPORTED: u16 = 0x6d00;
+const SW_CLA_NOT_SUPPORTED: u16 = 0x6e00;
+const SW_APP_NOT_FOUND: u16 = 0x6807;
+
+// blocks protocol, see `api::helpers`
+const HOST_START: u8 = 0;
+const HOST_GET_CHUNK_SUCCESS: u8 = 1;
+const HOST_PUT_CHUNK: u8 = 3;
+const HOST_RESULT_ACCUMULATING: u8 = 4;
+
+const LEDGER_RESULT_ACCUMULATING: u8 = 0;
+const LEDGER_RESULT_FINAL: u8 = 1;
+const LEDGER_GET_CHUNK: u8 = 2;
+const LEDGER_PUT_CHUNK: u8 = 3;
+
+const ZERO_HASH: [u8; 32] = [0u8; 32];
+
+#[derive(Clone, Debug)]
+pub struct EmulatorConfig {
+    /// BIP39 mnemonic the SLIP-10 keys are derived from
+    pub mnemonic: String,
+    /// Name reported by the app
+    pub app_name: String,
+    /// App version as (major, minor, patch)
+    pub version: (u8, u8, u8),
+    /// Start with the app open instead of the dashboard
+    pub app_open: bool,
+    /// Start with another app open, which only answers the BOLOS instructions
+    /// and has to be exited first. Takes precedence over `app_open`
+    pub other_app: Option<String>,
+    /// Max. number of result bytes per blocks protocol answer; longer results
+    /// are split using `ResultAccumulating`
+    pub result_chunk_size: usize,
+    /// Store results on the host with `PutChunk` and read them back with
+    /// `GetChunk` before returning them
+    pub offload_results: bool,
+    /// Reported in the app config
+    pub blind_signing: bool,
+    /// Reported in the app config
+    pub device: DeviceModel,
+}
+
+impl Default for EmulatorConfig {
+    fn default() -> Self {
+        EmulatorConfig {
+            mnemonic: DEFAULT_MNEMONIC.to_string(),
+            app_name: DEFAULT_APP_NAME.to_string(),
+            version: (1, 0, 0),
+            app_open: true,
+            other_app: None,
+            result_chunk_size: 32,
+            offload_results: true,
+            blind_signing: false,
+            device: DeviceModel::NanoSPlus,
+        }
+    }
+}
+
+enum Stage {
+    // waiting for the host to return the chunk with hash `next_hash`
+    Loading,
+    // waiting for the host to acknowledge the stored result
+    Offloading(Vec<u8>),
+    // waiting for the host to return the stored result
+    Reloading(Vec<u8>),
+    // result bytes that still have to be sent to the host
+    Returning(Vec<u8>),
+}
+
+struct Session {
+    ins: u8,
+    params: Vec<[u8; 32]>,
+    loaded: Vec<Vec<u8>>,
+    current: Vec<u8>,
+    next_hash: [u8; 32],
+    stage: Stage,
+}
+
+struct State {
+    app_open: bool,
+    other_app: Option<String>,
+    session: Option<Session>,
+}
+
+/// Software implementation of the IOTA Ledger app
+pub struct TransportEmulator {
+    config: EmulatorConfig,
+    seed: [u8; 64],
+    state: Mutex<State>,
+    account: Mutex<Option<u32>>,
+}
+
+impl TransportEmulator {
+    pub fn new(config: EmulatorConfig) -> Result<Self, LedgerError> {
+        let mnemonic = bip39::Mnemonic::parse(config.mnemonic.as_str())
+            .map_err(|_| LedgerError::InvalidMnemonic)?;
+        let seed = mnemonic.to_seed("");
+
+        Ok(TransportEmulator {
+            state: Mutex::new(State {
+                app_open: config.app_open && config.other_app.is_none(),
+                other_app: config.other_app.clone(),
+                session: None,
+            }),
+            config,
+            seed,
+            account: Mutex::new(None),
+        })
+    }
+
+    pub fn config(&self) -> &EmulatorConfig {
+        &self.config
+    }
+
+    /// Account selected with `SetAccount`
+    pub fn account(&self) -> Option<u32> {
+        *self.account.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn handle(&self, state: &mut State, cmd: &APDUCommand<Vec<u8>>) -> Result<Vec<u8>, u16> {
+        match cmd.cla {
+            APDU_BOLOS_CLA_B0 => self.handle_bolos_b0(state, cmd.ins),
+            APDU_BOLOS_CLA_E0 if !state.app_open && state.other_app.is_none() => {
+                self.handle_bolos_e0(state, cmd)
+            }
+            APDU_CLA if state.app_open => self.handle_blocks(state, cmd),
+            _ => Err(SW_CLA_NOT_SUPPORTED),
+        }
+    }
+
+    fn handle_bolos_b0(&self, state: &mut State, ins: u8) -> Result<Vec<u8>, u16> {
+        if ins == APDUInstructionsBolos::GetAppVersionB0.ins() {
+            let (name, version, flags) = if let Some(app) = &state.other_app {
+                (app.clone(), "1.0.0".to_string(), vec![0x02])
+            } else if state.app_open {
+                let (major, minor, patch) = self.config.version;
+                (
+                    self.config.app_name.clone(),
+                    format!("{major}.{minor}.{patch}"),
+                    vec![0x02],
+                )
+            } else {
+                ("BOLOS".to_string(), "2.0.0".to_string(), vec![])
+            };
+
+            let mut data = vec![0x01];
+            push_with_len(&mut data, name.as_bytes());
+            push_with_len(&mut data, version.as_bytes());
+            if !flags.is_empty() {
+                push_with_len(&mut data, &flags);
+            }
+            Ok(data)
+        } else if ins == APDUInstructionsBolos::AppExitB0.ins()
+            && (state.app_open || state.other_app.is_some())
+        {
+            state.app_open = false;
+            state.other_app = None;
+            state.session = None;
+            Ok(vec![])
+        } else {
+            Err(SW_INS_NOT_SUPPORTED)
+        }
+    }
+
+    fn handle_bolos_e0(
+        &self,
+        state: &mut State,
+        cmd: &APDUCommand<Vec<u8>>,
+    ) -> Result<Vec<u8>, u16> {
+        if cmd.ins == APDUInstructionsBolos::GetDeviceInfoE0.ins() {
+            let mut data = self.config.device.target_id().to_be_bytes().to_vec();
+            push_with_len(&mut data, b"2.0.0");
+            push_with_len(&mut data, &[0; 4]);
+            push_with_len(&mut data, b"1.0");
+            return Ok(data);
+        }
+        if cmd.ins != APDUInstructionsBolos::OpenAppE0.ins() {
+            return Err(SW_INS_NOT_SUPPORTED);
+        }
+        if cmd.data != self.config.app_name.as_bytes() {
+            return Err(SW_APP_NOT_FOUND);
+        }
+        state.app_open = true;
+        Ok(vec![])
+    }
+
+    fn handle_blocks(&self, state: &mut State, cmd: &APDUCommand<Vec<u8>>) -> Result<Vec<u8>, u16> {
+        let (&host_ins, payload) = cmd.data.split_first().ok_or(SW_WRONG_LENGTH)?;
+
+        if host_ins == HOST_START {
+            if !payload.len().is_multiple_of(32) {
+                state.session = None;
+                return Err(SW_WRONG_LENGTH);
+            }
+            let params: Vec<[u8; 32]> = payload
+                .chunks(32)
+                .map(|c| c.try_into().expect("chunks of 32 bytes"))
+                .collect();
+            state.session = Some(Session {
+                ins: cmd.ins,
+                next_hash: params.first().copied().unwrap_or(ZERO_HASH),
+                params,
+                loaded: Vec::new(),
+                current: Vec::new(),
+                stage: Stage::Loading,
+            });
+        } else {
+            let Some(session) = state.session.as_mut() else {
+                return Err(SW_DATA_INVALID);
+            };
+            if session.ins != cmd.ins {
+                state.session = None;
+                return Err(SW_DATA_INVALID);
+            }
+
+            let stage = std::mem::replace(&mut session.stage, Stage::Loading);
+            let accepted = match (host_ins, stage) {
+                (HOST_GET_CHUNK_SUCCESS, Stage::Loading) => {
+                    if payload.len() < 32 || Sha256::digest(payload).digest != session.next_hash {
+                        false
+                    } else {
+                        session.next_hash.copy_from_slice(&payload[..32]);
+                        session.current.extend_from_slice(&payload[32..]);
+                        true
+                    }
+                }
+                (HOST_PUT_CHUNK, Stage::Offloading(result)) => {
+                    session.stage = Stage::Reloading(result);
+                    true
+                }
+                (HOST_GET_CHUNK_SUCCESS, Stage::Reloading(result)) => {
+                    let accepted = payload == result.as_slice();
+                    session.stage = Stage::Returning(result);
+                    accepted
+                }
+                (HOST_RESULT_ACCUMULATING, Stage::Returning(rest)) => {
+                    session.stage = Stage::Returning(rest);
+                    true
+                }
+                // includes `GetChunkResponseFailure`, the app can't continue without the chunk
+                _ => false,
+            };
+            if !accepted {
+                state.session = None;
+                return Err(SW_DATA_INVALID);
+            }
+        }
+
+        self.advance(state)
+    }
+
+    // run the app until it needs something from the host again
+    fn advance(&self, state: &mut State) -> Result<Vec<u8>, u16> {
+        let session = state.session.as_mut().expect("active session");
+
+        if matches!(session.stage, Stage::Loading) {
+            // a zero hash terminates a parameter (empty parameters have no chunks at all)
+            while session.next_hash == ZERO_HASH && session.loaded.len() < session.params.len() {
+                session.loaded.push(std::mem::take(&mut session.current));
+                if let Some(next_hash) = session.params.get(session.loaded.len()) {
+                    session.next_hash = *next_hash;
+                }
+            }
+
+            if session.next_hash != ZERO_HASH {
+                return Ok(answer(LEDGER_GET_CHUNK, &session.next_hash));
+            }
+
+            let result = match self.execute(session.ins, &session.loaded) {
+                Ok(result) => result,
+                Err(sw) => {
+                    state.session = None;
+                    return Err(sw);
+                }
+            };
+
+            if self.config.offload_results && !result.is_empty() {
+                let data = answer(LEDGER_PUT_CHUNK, &result);
+                session.stage = Stage::Offloading(result);
+                return Ok(data);
+            }
+            session.stage = Stage::Returning(result);
+        }
+
+        match &mut session.stage {
+            Stage::Reloading(result) => {
+                Ok(answer(LEDGER_GET_CHUNK, &Sha256::digest(result).digest))
+            }
+            Stage::Returning(rest) if rest.len() > self.config.result_chunk_size => {
+                let tail = rest.split_off(self.config.result_chunk_size);
+                let data = answer(LEDGER_RESULT_ACCUMULATING, rest);
+                *rest = tail;
+                Ok(data)
+            }
+            Stage::Returning(rest) => {
+                let data = answer(LEDGER_RESULT_FINAL, rest);
+                state.session = None;
+                Ok(data)
+            }
+            _ => unreachable!("no pending host request"),
+        }
+    }
+
+    fn execute(&self, ins: u8, params: &[Vec<u8>]) -> Result<Vec<u8>, u16> {
+        match ins {
+            ins if ins == APDUInstructions::GetVersion as u8 => {
+                let (major, minor, patch) = self.config.version;
+                let mut data = vec![major, minor, patch];
+                data.extend_from_slice(self.config.app_name.as_bytes());
+                Ok(data)
+            }
+            ins if ins == APDUInstructions::GetPublicKey as u8
+                || ins == APDUInstructions::VerifyAddress as u8 =>
+            {
+                let path = unpack_path(params.first().ok_or(SW_WRONG_LENGTH)?)?;
+                let key_pair = derive_key_pair(&self.seed, &path)?;
+                let public_key = key_pair.public();
+                let address = IotaAddress::from(public_key);
+
+                let mut data = Vec::with_capacity(66);
+                push_with_len(&mut data, public_key.as_ref());
+                push_with_len(&mut data, &address.to_inner());
+                Ok(data)
+            }
+            ins if ins == APDUInstructions::GetAppConfig as u8 => {
+                let (major, minor, patch) = self.config.version;
+                let flags = if self.config.blind_signing {
+                    FLAG_BLIND_SIGNING
+                } else {
+                    0
+                };
+                Ok(vec![
+                    major,
+                    minor,
+                    patch,
+                    flags,
+                    self.config.device.app_id(),
+                    0,
+                ])
+            }
+            ins if ins == APDUInstructions::SetAccount as u8 => {
+                let account = params.first().ok_or(SW_WRONG_LENGTH)?;
+                let account: [u8; 4] =
+                    account.as_slice().try_into().map_err(|_| SW_WRONG_LENGTH)?;
+                *self.account.lock().unwrap_or_else(PoisonError::into_inner) =
+                    Some(u32::from_le_bytes(account));
+                Ok(Vec::new())
+            }
+            ins if ins == APDUInstructions::SignTransaction as u8 => {
+                if params.len() < 2 {
+                    return Err(SW_WRONG_LENGTH);
+                }
+                let transaction = unpack_transaction(&params[0])?;
+                let path = unpack_path(&params[1])?;
+                let key_pair = derive_key_pair(&self.seed, &path)?;
+
+                let digest = Blake2b256::digest(transaction);
+                let signature = key_pair.sign(&digest.digest);
+                Ok(signature.as_ref().to_vec())
+            }
+            _ => Err(SW_INS_NOT_SUPPORTED),
+        }
+    }
+}
+
+impl LedgerTransport for TransportEmulator {
+    fn exchange(
+        &self,
+        apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError> {
+        let mut state = self.state.lock().map_err(|_| LedgerError::TransportError)?;
+
+        let (mut raw, sw) = match self.handle(&mut state, apdu_command) {
+            Ok(data) => (data, SW_OK),
+            Err(sw) => (Vec::new(), sw),
+        };
+        raw.extend_from_slice(&sw.to_be_bytes());
+
+        APDUAnswer::from_answer(raw).map_err(|_| LedgerError::TransportError)
+    }
+}
+
+fn answer(instruction: u8, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(1 + payload.len());
+    data.push(instruction);
+    data.extend_from_slice(payload);
+    data
+}
+
+fn push_with_len(data: &mut Vec<u8>, bytes: &[u8]) {
+    data.push(bytes.len() as u8);
+    data.extend_from_slice(bytes);
+}
+
+fn unpack_path(param: &[u8]) -> Result<Vec<u32>, u16> {
+    let (&len, indices) = param.split_first().ok_or(SW_WRONG_LENGTH)?;
+    if indices.len() != len as usize * 4 {
+        return Err(SW_WRONG_LENGTH);
+    }
+    Ok(indices
+        .chunks(4)
+        .map(|c| u32::from_le_bytes(c.try_into().expect("chunks of 4 bytes")))
+        .collect())
+}
+
+fn unpack_transaction(param: &[u8]) -> Result<&[u8], u16> {
+    if param.len() < 4 {
+        return Err(SW_WRONG_LENGTH);
+    }
+    let (len, transaction) = param.split_at(4);
+    let len = u32::from_le_bytes(len.try_into().expect("4 bytes"));
+    if transaction.len() != len as usize {
+        return Err(SW_WRONG_LENGTH);
+    }
+    Ok(transaction)
+}
+
+// SLIP-10 derivation for ed25519, which only supports hardened indices
+fn derive_key_pair(seed: &[u8], path: &[u32]) -> Result<Ed25519KeyPair, u16> {
+    fn hmac_sha512(key: &[u8], parts: &[&[u8]]) -> [u8; 64] {
+        let mut mac =
+            Hmac::<sha2::Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        for part in parts {
+            mac.update(part);
+        }
+        let mut out = [0u8; 64];
+        out.copy_from_slice(&mac.finalize().into_bytes());
+        out
+    }
+
+    let mut i = hmac_sha512(b"ed25519 seed", &[seed]);
+    for index in path {
+        if index & HARDENED == 0 {
+            return Err(SW_BAD_DATA);
+        }
+        i = hmac_sha512(&i[32..], &[&[0u8], &i[..32], &index.to_be_bytes()]);
+    }
+
+    let private_key = Ed25519PrivateKey::from_bytes(&i[..32]).map_err(|_| SW_BAD_DATA)?;
+    Ok(Ed25519KeyPair::from(private_key))
+}
@@ -0,0 +1,95 @@
+use hidapi::{DeviceInfo, HidApi};
+use ledger_transport_hid::{LedgerHIDError, TransportNativeHID};
+use log::debug;
+
+use crate::{LedgerError, device::DeviceModel};
+
+/// A Ledger device connected via USB HID
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerDevice {
+    pub path: String,
+    pub serial_number: Option<String>,
+    pub product_id: u16,
+    pub model: DeviceModel,
+}
+
+impl From<&DeviceInfo> for LedgerDevice {
+    fn from(info: &DeviceInfo) -> Self {
+        LedgerDevice {
+            path: info.path().to_string_lossy().into_owned(),
+            serial_number: info.serial_number().map(str::to_string),
+            product_id: info.product_id(),
+            model: DeviceModel::from_product_id(info.product_id()),
+        }
+    }
+}
+
+/// Selects which of the connected Ledger devices to open
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// First device ordered by HID path
+    #[default]
+    First,
+    /// Device with the given HID path
+    Path(String),
+    /// Device with the given USB serial number
+    SerialNumber(String),
+}
+
+impl DeviceSelector {
+    fn matches(&self, device: &LedgerDevice) -> bool {
+        match self {
+            DeviceSelector::First => true,
+            DeviceSelector::Path(path) => &device.path == path,
+            DeviceSelector::SerialNumber(serial) => {
+                device.serial_number.as_deref() == Some(serial.as_str())
+            }
+        }
+    }
+}
+
+fn hid_api() -> Result<HidApi, LedgerError> {
+    HidApi::new().map_err(|e| {
+        debug!("HID error: {e}");
+        LedgerError::TransportError
+    })
+}
+
+// ledgers sorted by path so selecting the first one is deterministic
+fn ledgers(api: &HidApi) -> Vec<&DeviceInfo> {
+    let mut ledgers: Vec<&DeviceInfo> = TransportNativeHID::list_ledgers(api).collect();
+    ledgers.sort_by(|a, b| a.path().cmp(b.path()));
+    ledgers
+}
+
+/// List all connected Ledger devices
+pub fn list_devices() -> Result<Vec<LedgerDevice>, LedgerError> {
+    let api = hid_api()?;
+    Ok(ledgers(&api).into_iter().map(LedgerDevice::from).collect())
+}
+
+pub(crate) fn find_device(selector: &DeviceSelector) -> Result<LedgerDevice, LedgerError> {
+    let api = hid_api()?;
+    ledgers(&api)
+        .into_iter()
+        .map(LedgerDevice::from)
+        .find(|device| selector.matches(device))
+        .ok_or(LedgerError::DeviceNotFound)
+}
+
+pub(crate) fn open_device(device: &LedgerDevice) -> Result<TransportNativeHID, LedgerError> {
+    let api = hid_api()?;
+    let info = ledgers(&api)
+        .into_iter()
+        .find(|info| info.path().to_string_lossy() == device.path)
+        .ok_or(LedgerError::DeviceNotFound)?;
+
+    debug!("opening {} at {}", device.model, device.path);
+    TransportNativeHID::open_device(&api, info).map_err(|e| match e {
+        LedgerHIDError::DeviceNotFound => LedgerError::DeviceNotFound,
+        e => {
+            debug!("HID transport error: {e}");
+            LedgerError::TransportError
+        }
+    })
+}
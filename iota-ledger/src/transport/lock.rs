@@ -0,0 +1,126 @@
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions, TryLockError},
+    path::PathBuf,
+    sync::{Condvar, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use log::debug;
+
+use crate::LedgerError;
+
+lazy_static! {
+    // keys of the devices currently in use by this process
+    static ref LOCKED_DEVICES: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    static ref DEVICE_RELEASED: Condvar = Condvar::new();
+}
+
+// the OS has no way to wait for a file lock with a timeout
+const FILE_LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Exclusive access to a single device.
+///
+/// Always taken within the process, optionally also across processes using an
+/// advisory lock file keyed by the device (path).
+pub(crate) struct DeviceLock {
+    key: String,
+    _file: Option<File>,
+}
+
+impl DeviceLock {
+    pub(crate) fn acquire(
+        key: &str,
+        timeout: Duration,
+        file_lock: bool,
+    ) -> Result<Self, LedgerError> {
+        let deadline = Instant::now() + timeout;
+        let unavailable = || {
+            if timeout.is_zero() {
+                LedgerError::DeviceBusy
+            } else {
+                LedgerError::Timeout
+            }
+        };
+
+        let mut locked = LOCKED_DEVICES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while locked.contains(key) {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(unavailable());
+            }
+            debug!("waiting for {key} to be released...");
+            locked = DEVICE_RELEASED
+                .wait_timeout(locked, deadline - now)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        locked.insert(key.to_string());
+        drop(locked);
+
+        // from here on dropping the lock releases the device again
+        let mut lock = DeviceLock {
+            key: key.to_string(),
+            _file: None,
+        };
+        if file_lock {
+            lock._file = Some(lock_file(key, deadline)?.ok_or_else(unavailable)?);
+        }
+        debug!("locked {key}");
+        Ok(lock)
+    }
+}
+
+impl Drop for DeviceLock {
+    fn drop(&mut self) {
+        LOCKED_DEVICES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.key);
+        DEVICE_RELEASED.notify_all();
+        debug!("released {}", self.key);
+    }
+}
+
+fn lock_file_path(key: &str) -> PathBuf {
+    let name: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    std::env::temp_dir().join(format!("iota-ledger-{name}.lock"))
+}
+
+// returns `None` if the lock is still held by someone else at the deadline
+fn lock_file(key: &str, deadline: Instant) -> Result<Option<File>, LedgerError> {
+    let path = lock_file_path(key);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| {
+            debug!("could not open lock file {}: {e}", path.display());
+            LedgerError::TransportError
+        })?;
+
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(Some(file)),
+            Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Error(e)) => {
+                debug!("could not lock {}: {e}", path.display());
+                return Err(LedgerError::TransportError);
+            }
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        debug!("waiting for {} to be released...", path.display());
+        std::thread::sleep(FILE_LOCK_POLL_INTERVAL.min(deadline - now));
+    }
+}
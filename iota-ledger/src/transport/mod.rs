@@ -1,48 +1,256 @@
 use std::{
-    sync::{Arc, Mutex},
+    fmt,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
     time::{Duration, Instant},
 };
 
 use hex::ToHex;
-use lazy_static::lazy_static;
 use ledger_transport::{APDUAnswer, APDUCommand};
-use ledger_transport_hid::{LedgerHIDError, TransportNativeHID};
-use ledger_transport_tcp::{Callback, TransportTCP};
-use log::debug;
+use ledger_transport_hid::TransportNativeHID;
+pub use ledger_transport_tcp::TcpTimeouts;
+use ledger_transport_tcp::{LedgerTCPError, TransportTCP};
+use log::{debug, warn};
 
-use crate::LedgerError;
+use crate::{LedgerError, device::DeviceModel};
 
-lazy_static! {
-    static ref TRANSPORT_MUTEX: Arc<Mutex<i32>> = Arc::new(Mutex::new(0));
+pub mod emulator;
+mod hid;
+mod lock;
+pub mod observer;
+pub mod trace;
+pub use emulator::{EmulatorConfig, TransportEmulator};
+pub use hid::{DeviceSelector, LedgerDevice, list_devices};
+use lock::DeviceLock;
+pub use observer::{ApduEvent, ApduObserver, BlocksProgress, BlocksStage, Instruction};
+pub use trace::{RecordingTransport, ReplayTransport};
+
+/// Environment variable holding the default simulator address (`host:port`)
+pub const SIMULATOR_ADDR_ENV: &str = "LEDGER_SIMULATOR_ADDR";
+
+/// Chunk size of the blocks protocol if nothing is known about the device
+pub const DEFAULT_CHUNK_SIZE: usize = 180;
+/// Largest chunk that fits into an APDU next to the response byte and the
+/// 32 byte hash linking the chunks
+pub const MAX_CHUNK_SIZE: usize = 255 - 1 - 32;
+
+const DEFAULT_SIMULATOR_HOST: &str = "127.0.0.1";
+const DEFAULT_SIMULATOR_PORT: u16 = 9999;
+
+/// Address of a simulator (e.g. Speculos) reachable via TCP
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TcpConfig {
+    pub host: String,
+    pub port: u16,
+    pub timeouts: TcpTimeouts,
+}
+
+impl TcpConfig {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        TcpConfig {
+            host: host.into(),
+            port,
+            timeouts: TcpTimeouts::default(),
+        }
+    }
+
+    pub fn with_timeouts(mut self, timeouts: TcpTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+}
+
+impl Default for TcpConfig {
+    /// Taken from `LEDGER_SIMULATOR_ADDR`, falls back to `127.0.0.1:9999`
+    fn default() -> Self {
+        if let Ok(addr) = std::env::var(SIMULATOR_ADDR_ENV) {
+            match addr.parse() {
+                Ok(config) => return config,
+                Err(_) => warn!("ignoring invalid {SIMULATOR_ADDR_ENV}: {addr}"),
+            }
+        }
+        TcpConfig::new(DEFAULT_SIMULATOR_HOST, DEFAULT_SIMULATOR_PORT)
+    }
+}
+
+impl FromStr for TcpConfig {
+    type Err = LedgerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || LedgerError::InvalidAddress(s.to_string());
+        let (host, port) = s.rsplit_once(':').ok_or_else(invalid)?;
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(TcpConfig::new(host, port.parse().map_err(|_| invalid())?))
+    }
 }
 
-use std::sync::MutexGuard;
+impl fmt::Display for TcpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
 
-#[derive(Copy, Clone)]
+#[derive(Clone, Debug)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum TransportTypes {
-    TCP,
-    NativeHID,
+    TCP(TcpConfig),
+    NativeHID(DeviceSelector),
 }
 
-pub struct Transport {
-    pub transport: LedgerTransport,
-    _transport_mutex: MutexGuard<'static, i32>,
+impl TransportTypes {
+    /// The simulator at `address` (`host:port`), the default one if `None`,
+    /// see [`TcpConfig::default`]
+    pub fn simulator(address: Option<&str>) -> Result<Self, LedgerError> {
+        let config = match address {
+            Some(addr) => addr.parse()?,
+            None => TcpConfig::default(),
+        };
+        Ok(TransportTypes::TCP(config))
+    }
+
+    /// The simulator if requested or an address is given, the first HID
+    /// device otherwise
+    pub fn select(simulator: bool, address: Option<&str>) -> Result<Self, LedgerError> {
+        if simulator || address.is_some() {
+            Self::simulator(address)
+        } else {
+            Ok(TransportTypes::NativeHID(DeviceSelector::default()))
+        }
+    }
+}
+
+/// A channel that can exchange APDUs with a Ledger device (or something that
+/// behaves like one).
+///
+/// Implement this trait to plug custom transports (proxies, mocks, ...) into
+/// [`crate::LedgerHardwareWallet`].
+pub trait LedgerTransport: Send + Sync {
+    fn exchange(
+        &self,
+        apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError>;
 }
 
-impl Drop for Transport {
-    fn drop(&mut self) {
-        debug!("transport_mutex released");
+impl LedgerTransport for TransportTCP {
+    fn exchange(
+        &self,
+        apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError> {
+        TransportTCP::exchange(self, apdu_command).map_err(|e| {
+            debug!("TCP transport error: {e}");
+            match e {
+                LedgerTCPError::Io(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+                    ) =>
+                {
+                    LedgerError::Timeout
+                }
+                _ => LedgerError::TransportError,
+            }
+        })
     }
 }
 
-#[allow(clippy::upper_case_acronyms)]
-pub enum LedgerTransport {
-    TCP(TransportTCP),
-    NativeHID(TransportNativeHID),
+impl LedgerTransport for TransportNativeHID {
+    fn exchange(
+        &self,
+        apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError> {
+        TransportNativeHID::exchange(self, apdu_command).map_err(|e| {
+            debug!("HID transport error: {e}");
+            LedgerError::TransportError
+        })
+    }
+}
+
+pub struct Transport {
+    pub transport: Box<dyn LedgerTransport>,
+    device: Option<LedgerDevice>,
+    observers: Vec<Arc<dyn ApduObserver>>,
+    chunk_size: Option<usize>,
+    // 0 until a device model is known
+    negotiated_chunk_size: AtomicUsize,
+    _lock: Option<DeviceLock>,
 }
 
-impl LedgerTransport {
+impl Transport {
+    /// Wrap a custom transport.
+    ///
+    /// Custom transports don't take a device lock, it's up to the caller to
+    /// avoid concurrent access.
+    pub fn new(transport: impl LedgerTransport + 'static) -> Self {
+        Transport {
+            transport: Box::new(transport),
+            device: None,
+            observers: Vec::new(),
+            chunk_size: None,
+            negotiated_chunk_size: AtomicUsize::new(0),
+            _lock: None,
+        }
+    }
+
+    /// Use a fixed chunk size for the blocks protocol instead of the one
+    /// negotiated from the device model, capped at [`MAX_CHUNK_SIZE`]
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = Some(checked_chunk_size(chunk_size));
+    }
+
+    /// Chunk size used by the blocks protocol
+    ///
+    /// Either the configured one, the one negotiated from the device model
+    /// reported by the app or the one of the HID device's model.
+    pub fn chunk_size(&self) -> usize {
+        if let Some(chunk_size) = self.chunk_size {
+            return chunk_size;
+        }
+        match self.negotiated_chunk_size.load(Ordering::Relaxed) {
+            0 => self
+                .device
+                .as_ref()
+                .map_or(DEFAULT_CHUNK_SIZE, |device| device.model.max_chunk_size()),
+            chunk_size => chunk_size,
+        }
+    }
+
+    // called once the app reported the model it runs on
+    pub(crate) fn negotiate_chunk_size(&self, model: DeviceModel) {
+        if model == DeviceModel::Unknown {
+            return;
+        }
+        let chunk_size = model.max_chunk_size();
+        if self.chunk_size.is_none()
+            && self
+                .negotiated_chunk_size
+                .swap(chunk_size, Ordering::Relaxed)
+                != chunk_size
+        {
+            debug!("using chunk size {chunk_size} for {model}");
+        }
+    }
+
+    /// Register an observer called around every exchange
+    pub fn add_observer(&mut self, observer: impl ApduObserver + 'static) {
+        self.observers.push(Arc::new(observer));
+    }
+
+    pub(crate) fn report_progress(&self, progress: &BlocksProgress) {
+        for observer in &self.observers {
+            observer.blocks_progress(progress);
+        }
+    }
+
+    /// The HID device this transport is connected to, if any
+    pub fn device(&self) -> Option<&LedgerDevice> {
+        self.device.as_ref()
+    }
+
     pub(crate) fn exchange(
         &self,
         apdu_command: &APDUCommand<Vec<u8>>,
@@ -51,58 +259,137 @@ impl LedgerTransport {
             "Exchanging APDU command: {}",
             apdu_command.serialize().encode_hex::<String>()
         );
-        match self {
-            LedgerTransport::TCP(t) => t
-                .exchange(apdu_command)
-                .map_err(|_| LedgerError::TransportError),
-            LedgerTransport::NativeHID(h) => h
-                .exchange(apdu_command)
-                .map_err(|_| LedgerError::TransportError),
+
+        let instruction = Instruction::decode(apdu_command);
+        for observer in &self.observers {
+            observer.before_exchange(instruction, apdu_command);
+        }
+
+        let start = Instant::now();
+        let result = self.transport.exchange(apdu_command);
+
+        let event = ApduEvent {
+            instruction,
+            command: apdu_command,
+            result: result.as_ref(),
+            elapsed: start.elapsed(),
+        };
+        for observer in &self.observers {
+            observer.after_exchange(&event);
         }
+        result
     }
 }
 
-fn try_get_lock(timeout: Duration) -> Result<MutexGuard<'static, i32>, LedgerError> {
-    let start_time = Instant::now();
-    while start_time.elapsed() < timeout {
-        match TRANSPORT_MUTEX.try_lock() {
-            Ok(guard) => {
-                return Ok(guard);
-            }
-            Err(_) => {
-                debug!("trying to acquire transport_mutex lock...");
-            }
+// allows wrapping an opened (and locked) device, e.g. for recording
+impl LedgerTransport for Transport {
+    fn exchange(
+        &self,
+        apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError> {
+        Transport::exchange(self, apdu_command)
+    }
+}
+
+/// Options for opening a transport
+#[derive(Clone)]
+pub struct TransportOptions {
+    /// How long to wait for a device that is in use
+    pub lock_timeout: Duration,
+    /// Also lock the device against other processes using an advisory lock
+    /// file in the temp directory
+    pub file_lock: bool,
+    /// Called around every exchange, see [`ApduObserver`]
+    pub observers: Vec<Arc<dyn ApduObserver>>,
+    /// Fixed chunk size of the blocks protocol, negotiated from the device
+    /// model if `None`, see [`Transport::chunk_size`]
+    pub chunk_size: Option<usize>,
+}
+
+impl TransportOptions {
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    pub fn with_observer(mut self, observer: impl ApduObserver + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+}
+
+impl fmt::Debug for TransportOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransportOptions")
+            .field("lock_timeout", &self.lock_timeout)
+            .field("file_lock", &self.file_lock)
+            .field("observers", &self.observers.len())
+            .field("chunk_size", &self.chunk_size)
+            .finish()
+    }
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
+        TransportOptions {
+            lock_timeout: Duration::from_secs(30),
+            file_lock: false,
+            observers: Vec::new(),
+            chunk_size: None,
         }
-        std::thread::sleep(Duration::from_secs(1));
     }
-    Err(LedgerError::Timeout)
 }
 
 // only create transport without IOTA specific calls
-pub fn create_transport(
+pub fn create_transport(transport_type: TransportTypes) -> Result<Transport, LedgerError> {
+    create_transport_with_options(transport_type, &TransportOptions::default())
+}
+
+/// Like [`create_transport`] but fails with [`LedgerError::DeviceBusy`]
+/// right away if the device is in use
+pub fn try_open(transport_type: TransportTypes) -> Result<Transport, LedgerError> {
+    let options = TransportOptions {
+        lock_timeout: Duration::ZERO,
+        ..Default::default()
+    };
+    create_transport_with_options(transport_type, &options)
+}
+
+pub fn create_transport_with_options(
     transport_type: TransportTypes,
-    callback: Option<Callback>,
+    options: &TransportOptions,
 ) -> Result<Transport, LedgerError> {
-    debug!("transport_mutex try lock");
-    let transport_mutex = try_get_lock(Duration::from_secs(30))?;
-    debug!("transport_mutex locked");
-    let transport = match transport_type {
-        TransportTypes::TCP => Transport {
-            _transport_mutex: transport_mutex,
-            transport: LedgerTransport::TCP(TransportTCP::new("127.0.0.1", 9999, callback)),
-        },
-        TransportTypes::NativeHID => {
-            let api = hidapi::HidApi::new().map_err(|_| LedgerError::TransportError)?;
-            Transport {
-                _transport_mutex: transport_mutex,
-                transport: LedgerTransport::NativeHID(TransportNativeHID::new(&api).map_err(
-                    |e| match e {
-                        LedgerHIDError::DeviceNotFound => LedgerError::DeviceNotFound,
-                        _ => LedgerError::TransportError,
-                    },
-                )?),
-            }
+    let (transport, device, lock): (Box<dyn LedgerTransport>, _, _) = match transport_type {
+        TransportTypes::TCP(config) => {
+            let lock = DeviceLock::acquire(
+                &format!("tcp:{config}"),
+                options.lock_timeout,
+                options.file_lock,
+            )?;
+            let transport =
+                TransportTCP::with_timeouts(&config.host, config.port, config.timeouts, None);
+            (Box::new(transport), None, lock)
+        }
+        TransportTypes::NativeHID(selector) => {
+            let device = hid::find_device(&selector)?;
+            let lock = DeviceLock::acquire(&device.path, options.lock_timeout, options.file_lock)?;
+            (Box::new(hid::open_device(&device)?), Some(device), lock)
         }
     };
-    Ok(transport)
+    Ok(Transport {
+        transport,
+        device,
+        observers: options.observers.clone(),
+        chunk_size: options.chunk_size.map(checked_chunk_size),
+        negotiated_chunk_size: AtomicUsize::new(0),
+        _lock: Some(lock),
+    })
+}
+
+fn checked_chunk_size(chunk_size: usize) -> usize {
+    let checked = chunk_size.clamp(1, MAX_CHUNK_SIZE);
+    if checked != chunk_size {
+        warn!("chunk size {chunk_size} out of range, using {checked}");
+    }
+    checked
 }
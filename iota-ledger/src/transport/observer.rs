@@ -0,0 +1,108 @@
+//! Hooks observing every APDU exchanged with a device.
+
+use std::{sync::mpsc::Sender, time::Duration};
+
+use ledger_transport::{APDUAnswer, APDUCommand};
+
+use crate::{
+    LedgerError,
+    api::constants::{APDU_CLA, APDUInstructions, APDUInstructionsBolos},
+};
+
+/// Instruction of an APDU command
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// Instruction handled by the IOTA app
+    App(APDUInstructions),
+    /// Instruction handled by the dashboard / OS
+    Bolos(APDUInstructionsBolos),
+    Unknown {
+        cla: u8,
+        ins: u8,
+    },
+}
+
+impl Instruction {
+    pub fn decode(command: &APDUCommand<Vec<u8>>) -> Self {
+        let (cla, ins) = (command.cla, command.ins);
+
+        match cla {
+            APDU_CLA => APDUInstructions::try_from(ins).ok().map(Instruction::App),
+            _ => APDUInstructionsBolos::ALL
+                .into_iter()
+                .find(|instruction| instruction.cla() == cla && instruction.ins() == ins)
+                .map(Instruction::Bolos),
+        }
+        .unwrap_or(Instruction::Unknown { cla, ins })
+    }
+}
+
+/// A finished exchange
+pub struct ApduEvent<'a> {
+    pub instruction: Instruction,
+    pub command: &'a APDUCommand<Vec<u8>>,
+    /// The answer, or the error if the transport failed
+    pub result: Result<&'a APDUAnswer<Vec<u8>>, &'a LedgerError>,
+    /// Round trip time, includes waiting for the user on the device
+    pub elapsed: Duration,
+}
+
+impl ApduEvent<'_> {
+    /// Status word of the answer, `None` if the transport failed
+    pub fn status_word(&self) -> Option<u16> {
+        self.result.ok().map(APDUAnswer::retcode)
+    }
+}
+
+/// Stage of a blocks protocol transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlocksStage {
+    /// The device is fetching the request chunks
+    Sending,
+    /// All chunks are on the device, the next answer arrives once the user
+    /// confirmed (or rejected) on the device
+    AwaitingConfirmation,
+    Done,
+}
+
+/// Progress of a request sent with the blocks protocol
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlocksProgress {
+    pub instruction: APDUInstructions,
+    pub stage: BlocksStage,
+    /// Distinct chunks fetched by the device so far
+    pub chunks_served: usize,
+    pub chunks_total: usize,
+    pub bytes_served: usize,
+    pub bytes_total: usize,
+}
+
+/// Observer called around every exchange of a [`Transport`](super::Transport)
+///
+/// Closures taking an [`ApduEvent`] can be used directly, a
+/// [`Sender`] receives the [`BlocksProgress`] updates.
+pub trait ApduObserver: Send + Sync {
+    /// Called right before the command is sent
+    fn before_exchange(&self, _instruction: Instruction, _command: &APDUCommand<Vec<u8>>) {}
+
+    fn after_exchange(&self, _event: &ApduEvent<'_>) {}
+
+    /// Called whenever a blocks protocol transfer advanced
+    fn blocks_progress(&self, _progress: &BlocksProgress) {}
+}
+
+impl<F> ApduObserver for F
+where
+    F: Fn(&ApduEvent<'_>) + Send + Sync,
+{
+    fn after_exchange(&self, event: &ApduEvent<'_>) {
+        self(event)
+    }
+}
+
+impl ApduObserver for Sender<BlocksProgress> {
+    fn blocks_progress(&self, progress: &BlocksProgress) {
+        // a dropped receiver only means nobody is listening anymore
+        let _ = self.send(progress.clone());
+    }
+}
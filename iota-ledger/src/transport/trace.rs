@@ -0,0 +1,265 @@
+//! Recording and replaying of APDU exchanges.
+//!
+//! Traces are plain text, one line per APDU:
+//!
+//! ```text
+//! # iota-ledger apdu trace v1
+//! > 0 b001000000
+//! < 12 0104494f544105312e302e3001029000
+//! ```
+//!
+//! `>` lines are commands sent to the device, `<` lines the answers including
+//! the trailing status word, or `error` if the exchange failed in the
+//! transport. The number is the time in milliseconds since the start of the
+//! recording at which the command was sent or the answer was received. Empty
+//! lines and lines starting with `#` are ignored.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use ledger_transport::{APDUAnswer, APDUCommand};
+use log::{debug, error};
+
+use crate::{LedgerError, transport::LedgerTransport};
+
+const TRACE_HEADER: &str = "# iota-ledger apdu trace v1";
+const TRACE_ERROR: &str = "error";
+
+/// A single recorded exchange
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Serialized APDU command
+    pub command: Vec<u8>,
+    /// Raw answer including the status word, `None` if the exchange failed
+    pub answer: Option<Vec<u8>>,
+    /// Time since the start of the recording the command was sent at
+    pub sent: Duration,
+    /// Time since the start of the recording the answer was received at
+    pub received: Duration,
+}
+
+/// Transport wrapper writing every exchange to a trace
+pub struct RecordingTransport<T: LedgerTransport> {
+    inner: T,
+    writer: Mutex<Box<dyn Write + Send>>,
+    start: Instant,
+}
+
+impl<T: LedgerTransport> RecordingTransport<T> {
+    pub fn new(inner: T, writer: impl Write + Send + 'static) -> Result<Self, LedgerError> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+        writeln!(writer, "{TRACE_HEADER}").map_err(trace_error)?;
+
+        Ok(RecordingTransport {
+            inner,
+            writer: Mutex::new(writer),
+            start: Instant::now(),
+        })
+    }
+
+    /// Record into the file at `path`, an existing file is truncated
+    pub fn create(inner: T, path: impl AsRef<Path>) -> Result<Self, LedgerError> {
+        let file = File::create(path).map_err(trace_error)?;
+        Self::new(inner, BufWriter::new(file))
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: LedgerTransport> LedgerTransport for RecordingTransport<T> {
+    fn exchange(
+        &self,
+        apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError> {
+        // held during the exchange to keep commands and answers paired
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        // written first, so the command is in the trace even if the exchange
+        // never returns
+        writeln!(
+            writer,
+            "> {} {}",
+            self.start.elapsed().as_millis(),
+            hex::encode(apdu_command.serialize())
+        )
+        .and_then(|_| writer.flush())
+        .map_err(trace_error)?;
+
+        let result = self.inner.exchange(apdu_command);
+        let received = self.start.elapsed();
+
+        let raw_answer = match &result {
+            Ok(answer) => {
+                let mut raw_answer = answer.data().to_vec();
+                raw_answer.extend_from_slice(&answer.retcode().to_be_bytes());
+                hex::encode(raw_answer)
+            }
+            Err(_) => TRACE_ERROR.to_string(),
+        };
+        writeln!(writer, "< {} {raw_answer}", received.as_millis())
+            .and_then(|_| writer.flush())
+            .map_err(trace_error)?;
+
+        result
+    }
+}
+
+/// Transport serving the answers of a recorded trace
+///
+/// Every command has to match the recorded one, otherwise the exchange fails
+/// with [`LedgerError::ReplayMismatch`]. Once a mismatch happened all further
+/// exchanges fail as well. Exchanges that failed while recording fail with
+/// [`LedgerError::TransportError`].
+pub struct ReplayTransport {
+    entries: Vec<TraceEntry>,
+    state: Mutex<ReplayState>,
+}
+
+struct ReplayState {
+    // index of the next entry
+    position: usize,
+    // index and command of the first mismatch
+    failed_at: Option<(usize, Vec<u8>)>,
+}
+
+impl ReplayTransport {
+    pub fn new(entries: Vec<TraceEntry>) -> Self {
+        ReplayTransport {
+            entries,
+            state: Mutex::new(ReplayState {
+                position: 0,
+                failed_at: None,
+            }),
+        }
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LedgerError> {
+        let file = File::open(path).map_err(trace_error)?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, LedgerError> {
+        let mut entries = Vec::new();
+        let mut pending: Option<(Duration, Vec<u8>)> = None;
+
+        for (number, line) in reader.lines().enumerate() {
+            let line = line.map_err(trace_error)?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || LedgerError::InvalidTrace(format!("line {}: {line}", number + 1));
+            let mut fields = line.split_whitespace();
+            let (Some(direction), Some(millis), Some(data), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid());
+            };
+            let time = Duration::from_millis(millis.parse().map_err(|_| invalid())?);
+            let data = match data {
+                TRACE_ERROR => None,
+                data => Some(hex::decode(data).map_err(|_| invalid())?),
+            };
+
+            match (direction, pending.take(), data) {
+                (">", None, Some(command)) => pending = Some((time, command)),
+                ("<", Some((sent, command)), answer) => entries.push(TraceEntry {
+                    command,
+                    answer,
+                    sent,
+                    received: time,
+                }),
+                _ => return Err(invalid()),
+            }
+        }
+
+        if pending.is_some() {
+            return Err(LedgerError::InvalidTrace(
+                "command without answer at the end of the trace".to_string(),
+            ));
+        }
+        Ok(Self::new(entries))
+    }
+
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Number of recorded exchanges that were not replayed yet
+    pub fn remaining(&self) -> usize {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        self.entries.len() - state.position
+    }
+
+    /// Fails if the session didn't replay the whole trace or a mismatch
+    /// happened
+    pub fn finish(&self) -> Result<(), LedgerError> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((index, command)) = &state.failed_at {
+            return Err(self.mismatch(*index, hex::encode(command)));
+        }
+        if state.position < self.entries.len() {
+            error!("APDU replay finished early at exchange {}", state.position);
+            return Err(self.mismatch(state.position, "end of session".to_string()));
+        }
+        Ok(())
+    }
+
+    fn mismatch(&self, index: usize, actual: String) -> LedgerError {
+        let expected = self
+            .entries
+            .get(index)
+            .map_or("end of trace".to_string(), |e| hex::encode(&e.command));
+        LedgerError::ReplayMismatch {
+            index,
+            expected,
+            actual,
+        }
+    }
+}
+
+impl LedgerTransport for ReplayTransport {
+    fn exchange(
+        &self,
+        apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError> {
+        let command = apdu_command.serialize();
+
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.failed_at.is_some() {
+            return Err(LedgerError::ReplayMismatch {
+                index: state.position,
+                expected: "nothing after a mismatch".to_string(),
+                actual: hex::encode(&command),
+            });
+        }
+
+        let index = state.position;
+        let entry = match self.entries.get(index) {
+            Some(entry) if entry.command == command => entry,
+            _ => {
+                let e = self.mismatch(index, hex::encode(&command));
+                error!("{e}");
+                state.failed_at = Some((index, command));
+                return Err(e);
+            }
+        };
+        state.position += 1;
+
+        debug!("replaying exchange {index}");
+        let answer = entry.answer.clone().ok_or(LedgerError::TransportError)?;
+        APDUAnswer::from_answer(answer)
+            .map_err(|_| LedgerError::InvalidTrace(format!("answer {index} too short")))
+    }
+}
+
+fn trace_error(e: std::io::Error) -> LedgerError {
+    LedgerError::InvalidTrace(e.to_string())
+}
@@ -0,0 +1,389 @@
+use std::{
+    sync::{Arc, Mutex, mpsc},
+    time::Duration,
+};
+
+use iota_ledger::{
+    AppOpenProgress, ClearSigningPolicy, IotaDerivationPath, LedgerError, LedgerHardwareWallet,
+    LedgerTransport, WalletConfig,
+    api::constants::APDUInstructions,
+    transport::{
+        ApduEvent, BlocksProgress, BlocksStage, EmulatorConfig, Instruction, TransportEmulator,
+    },
+};
+use iota_types::{base_types::ObjectID, crypto::IotaSignature, object::Object};
+use ledger_transport::{APDUAnswer, APDUCommand};
+use serde::Serialize;
+use shared_crypto::intent::{Intent, IntentMessage};
+
+fn emulator(config: EmulatorConfig) -> LedgerHardwareWallet {
+    LedgerHardwareWallet::with_transport(TransportEmulator::new(config).unwrap())
+}
+
+fn ledger(offload_results: bool) -> LedgerHardwareWallet {
+    emulator(EmulatorConfig {
+        offload_results,
+        ..Default::default()
+    })
+}
+
+fn objects() -> Vec<Object> {
+    (0..3)
+        .map(|_| Object::immutable_with_id_for_testing(ObjectID::random()))
+        .collect()
+}
+
+fn path() -> IotaDerivationPath {
+    "m/44'/4218'/0'/0'/1'".parse().unwrap()
+}
+
+fn sign_and_verify<T: Serialize>(
+    ledger: &LedgerHardwareWallet,
+    intent_msg: IntentMessage<T>,
+    objects: Vec<Object>,
+) {
+    let public_key = ledger.get_public_key(&path()).unwrap();
+    let signed = ledger.sign_intent(&path(), intent_msg, objects).unwrap();
+
+    assert_eq!(signed.address, public_key.address);
+    signed
+        .signature
+        .verify_secure(
+            &signed.intent_msg,
+            signed.address,
+            signed.signature.scheme(),
+        )
+        .unwrap();
+}
+
+#[test]
+fn get_public_key() {
+    for offload_results in [false, true] {
+        let ledger = ledger(offload_results);
+        let first = ledger.get_public_key(&path()).unwrap();
+        let second = ledger.get_public_key(&path()).unwrap();
+        assert_eq!(first.public_key, second.public_key);
+        assert_eq!(first.address, second.address);
+
+        let other = "m/44'/4218'/0'/0'/2'".parse().unwrap();
+        assert_ne!(
+            ledger.get_public_key(&other).unwrap().address,
+            first.address
+        );
+    }
+}
+
+#[test]
+fn sign_personal_message() {
+    for offload_results in [false, true] {
+        let intent_msg = IntentMessage::new(Intent::personal_message(), b"Hello IOTA".to_vec());
+        sign_and_verify(&ledger(offload_results), intent_msg, vec![]);
+    }
+}
+
+#[test]
+fn sign_transaction_without_objects() {
+    for offload_results in [false, true] {
+        // without objects the transaction is blind signed
+        let ledger = emulator(EmulatorConfig {
+            offload_results,
+            blind_signing: true,
+            ..Default::default()
+        });
+        // longer than a chunk, so the payload is split into several blocks
+        let intent_msg = IntentMessage::new(Intent::iota_transaction(), vec![0x42u8; 1000]);
+        sign_and_verify(&ledger, intent_msg, vec![]);
+    }
+}
+
+#[test]
+fn sign_transaction_with_objects() {
+    for offload_results in [false, true] {
+        let intent_msg = IntentMessage::new(Intent::iota_transaction(), vec![0x42u8; 1000]);
+        sign_and_verify(&ledger(offload_results), intent_msg, objects());
+    }
+}
+
+#[test]
+fn blind_signing_needs_setting() {
+    let intent_msg = IntentMessage::new(Intent::iota_transaction(), vec![0x42u8; 1000]);
+    assert!(matches!(
+        ledger(true).sign_intent(&path(), intent_msg, vec![]),
+        Err(LedgerError::BlindSigningDisabled(None))
+    ));
+}
+
+#[test]
+fn require_clear_signing() {
+    let ledger = emulator(EmulatorConfig {
+        blind_signing: true,
+        ..Default::default()
+    })
+    .with_config(WalletConfig {
+        clear_signing: ClearSigningPolicy::Require,
+        ..Default::default()
+    });
+
+    let intent_msg = IntentMessage::new(Intent::iota_transaction(), vec![0x42u8; 1000]);
+    assert!(matches!(
+        ledger.sign_intent(&path(), intent_msg, vec![]),
+        Err(LedgerError::ObjectsMissing)
+    ));
+
+    let intent_msg = IntentMessage::new(Intent::iota_transaction(), vec![0x42u8; 1000]);
+    sign_and_verify(&ledger, intent_msg, objects());
+    // personal messages have no objects
+    let intent_msg = IntentMessage::new(Intent::personal_message(), b"Hello IOTA".to_vec());
+    sign_and_verify(&ledger, intent_msg, vec![]);
+}
+
+// instructions of all exchanges, in order
+fn log_instructions(ledger: &mut LedgerHardwareWallet) -> Arc<Mutex<Vec<Instruction>>> {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let observer_log = log.clone();
+    ledger.add_observer(move |event: &ApduEvent<'_>| {
+        observer_log.lock().unwrap().push(event.instruction);
+    });
+    log
+}
+
+fn take(log: &Mutex<Vec<Instruction>>) -> Vec<Instruction> {
+    std::mem::take(&mut log.lock().unwrap())
+}
+
+// answers the next command with the instruction with the status word,
+// instead of forwarding it to the emulator
+#[derive(Clone, Default)]
+struct Fault(Arc<Mutex<Option<(APDUInstructions, u16)>>>);
+
+impl Fault {
+    fn set(&self, instruction: APDUInstructions, sw: u16) {
+        *self.0.lock().unwrap() = Some((instruction, sw));
+    }
+}
+
+struct FaultyEmulator {
+    emulator: TransportEmulator,
+    fault: Fault,
+}
+
+impl LedgerTransport for FaultyEmulator {
+    fn exchange(
+        &self,
+        apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError> {
+        let mut fault = self.fault.0.lock().unwrap();
+        match *fault {
+            Some((instruction, sw))
+                if Instruction::decode(apdu_command) == Instruction::App(instruction) =>
+            {
+                *fault = None;
+                Ok(APDUAnswer::from_answer(sw.to_be_bytes().to_vec()).unwrap())
+            }
+            _ => self.emulator.exchange(apdu_command),
+        }
+    }
+}
+
+fn faulty_ledger() -> (LedgerHardwareWallet, Fault) {
+    let fault = Fault::default();
+    let emulator = FaultyEmulator {
+        emulator: TransportEmulator::new(EmulatorConfig::default()).unwrap(),
+        fault: fault.clone(),
+    };
+    (LedgerHardwareWallet::with_transport(emulator), fault)
+}
+
+fn sign_message(ledger: &LedgerHardwareWallet) -> Result<(), LedgerError> {
+    let intent_msg = IntentMessage::new(Intent::personal_message(), b"Hello IOTA".to_vec());
+    ledger.sign_intent(&path(), intent_msg, vec![]).map(|_| ())
+}
+
+#[test]
+fn session_cached_between_signatures() {
+    let mut ledger = ledger(true);
+    let log = log_instructions(&mut ledger);
+
+    sign_message(&ledger).unwrap();
+    let first = take(&log);
+    assert!(first.contains(&Instruction::App(APDUInstructions::GetVersion)));
+    assert!(first.contains(&Instruction::App(APDUInstructions::GetPublicKey)));
+
+    sign_message(&ledger).unwrap();
+    let second = take(&log);
+    assert!(!second.is_empty());
+    assert!(
+        second
+            .iter()
+            .all(|i| *i == Instruction::App(APDUInstructions::SignTransaction)),
+        "{second:?}"
+    );
+}
+
+#[test]
+fn session_dropped_on_app_switch() {
+    let mut ledger = ledger(true);
+    let log = log_instructions(&mut ledger);
+
+    sign_message(&ledger).unwrap();
+    ledger.bolos_exit_app().unwrap();
+    ledger.bolos_open_app().unwrap();
+    take(&log);
+
+    sign_message(&ledger).unwrap();
+    let after_reopen = take(&log);
+    assert!(after_reopen.contains(&Instruction::App(APDUInstructions::GetVersion)));
+    assert!(after_reopen.contains(&Instruction::App(APDUInstructions::GetPublicKey)));
+}
+
+#[test]
+fn session_dropped_on_error() {
+    let (mut ledger, fault) = faulty_ledger();
+    let log = log_instructions(&mut ledger);
+
+    sign_message(&ledger).unwrap();
+    fault.set(APDUInstructions::SetAccount, 0x6a80);
+    assert!(ledger.set_account(1).is_err());
+    take(&log);
+
+    sign_message(&ledger).unwrap();
+    assert!(take(&log).contains(&Instruction::App(APDUInstructions::GetVersion)));
+}
+
+#[test]
+fn session_kept_without_app_config() {
+    let (mut ledger, fault) = faulty_ledger();
+    let log = log_instructions(&mut ledger);
+
+    // older apps don't implement `GetAppConfig`
+    fault.set(APDUInstructions::GetAppConfig, 0x6d00);
+    let capabilities = ledger.get_capabilities().unwrap();
+    assert_eq!(capabilities.blind_signing, None);
+    assert!(take(&log).contains(&Instruction::App(APDUInstructions::GetAppConfig)));
+
+    assert_eq!(ledger.get_capabilities().unwrap(), capabilities);
+    ledger.get_version().unwrap();
+    assert!(take(&log).is_empty());
+}
+
+#[test]
+fn session_kept_on_rejection() {
+    let (mut ledger, fault) = faulty_ledger();
+    let log = log_instructions(&mut ledger);
+
+    sign_message(&ledger).unwrap();
+    fault.set(APDUInstructions::SignTransaction, 0x6985);
+    assert!(matches!(
+        sign_message(&ledger),
+        Err(LedgerError::UserRejected(0x6985))
+    ));
+    take(&log);
+
+    sign_message(&ledger).unwrap();
+    let retry = take(&log);
+    assert!(
+        retry
+            .iter()
+            .all(|i| *i == Instruction::App(APDUInstructions::SignTransaction)),
+        "{retry:?}"
+    );
+}
+
+fn ensure_app_open(ledger: &LedgerHardwareWallet) -> Vec<AppOpenProgress> {
+    let mut steps = Vec::new();
+    ledger
+        .ensure_app_open(Duration::from_secs(5), |step| steps.push(step))
+        .unwrap();
+    steps
+}
+
+#[test]
+fn open_app_from_dashboard() {
+    let ledger = emulator(EmulatorConfig {
+        app_open: false,
+        ..Default::default()
+    });
+    assert!(!ledger.is_app_open().unwrap());
+
+    assert_eq!(
+        ensure_app_open(&ledger),
+        [AppOpenProgress::ConfirmAppOpen, AppOpenProgress::Open]
+    );
+    assert!(ledger.is_app_open().unwrap());
+    ledger.get_public_key(&path()).unwrap();
+
+    // nothing to do once the app runs
+    assert_eq!(ensure_app_open(&ledger), [AppOpenProgress::Open]);
+}
+
+#[test]
+fn open_app_from_other_app() {
+    let ledger = emulator(EmulatorConfig {
+        other_app: Some("Bitcoin".to_string()),
+        ..Default::default()
+    });
+    assert!(!ledger.is_app_open().unwrap());
+
+    assert_eq!(
+        ensure_app_open(&ledger),
+        [
+            AppOpenProgress::ExitingApp("Bitcoin".to_string()),
+            AppOpenProgress::ConfirmAppOpen,
+            AppOpenProgress::Open
+        ]
+    );
+    ledger.get_public_key(&path()).unwrap();
+}
+
+#[test]
+fn blocks_progress() {
+    let mut ledger = ledger(true);
+    let (sender, receiver) = mpsc::channel();
+    ledger.add_observer(sender);
+    // device to host instructions of the blocks protocol answers
+    let answers = Arc::new(Mutex::new(Vec::new()));
+    let observer_answers = answers.clone();
+    ledger.add_observer(move |event: &ApduEvent<'_>| {
+        if let (Instruction::App(APDUInstructions::SignTransaction), Ok(answer)) =
+            (event.instruction, event.result)
+        {
+            observer_answers
+                .lock()
+                .unwrap()
+                .extend(answer.data().first().copied());
+        }
+    });
+
+    let intent_msg = IntentMessage::new(Intent::iota_transaction(), vec![0x42u8; 1000]);
+    sign_and_verify(&ledger, intent_msg, objects());
+
+    let progress: Vec<BlocksProgress> = receiver
+        .try_iter()
+        .filter(|p| p.instruction == APDUInstructions::SignTransaction)
+        .collect();
+    let (done, rest) = progress.split_last().unwrap();
+    let (confirmation, sending) = rest.split_last().unwrap();
+
+    assert_eq!(done.stage, BlocksStage::Done);
+    assert_eq!(done.chunks_served, done.chunks_total);
+    assert_eq!(done.bytes_served, done.bytes_total);
+    assert_eq!(confirmation.stage, BlocksStage::AwaitingConfirmation);
+    assert_eq!(confirmation.chunks_served, confirmation.chunks_total);
+    // one report per chunk of the request
+    assert!(sending.iter().all(|p| p.stage == BlocksStage::Sending));
+    assert_eq!(sending.len(), done.chunks_total);
+    assert!(
+        sending
+            .iter()
+            .enumerate()
+            .all(|(i, p)| p.chunks_served == i + 1)
+    );
+
+    // the result was stored on the host and read back, which isn't counted
+    const GET_CHUNK: u8 = 2;
+    const PUT_CHUNK: u8 = 3;
+    let answers = answers.lock().unwrap();
+    assert!(answers.contains(&PUT_CHUNK));
+    let chunks_fetched = answers.iter().filter(|a| **a == GET_CHUNK).count();
+    assert!(chunks_fetched > done.chunks_total);
+}
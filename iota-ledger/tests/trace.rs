@@ -0,0 +1,151 @@
+use std::{
+    io::{Cursor, Write},
+    sync::{Arc, Mutex},
+};
+
+use iota_ledger::{
+    IotaDerivationPath, LedgerError, LedgerHardwareWallet, LedgerTransport, RecordingTransport,
+    ReplayTransport,
+    transport::{EmulatorConfig, TransportEmulator},
+};
+use ledger_transport::{APDUAnswer, APDUCommand};
+use shared_crypto::intent::{Intent, IntentMessage};
+
+// trace writer the test can read back after the wallet took the transport
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// lets the test call `finish` after the wallet took the transport
+#[derive(Clone)]
+struct SharedReplay(Arc<ReplayTransport>);
+
+impl LedgerTransport for SharedReplay {
+    fn exchange(
+        &self,
+        apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError> {
+        self.0.exchange(apdu_command)
+    }
+}
+
+fn path(index: u32) -> IotaDerivationPath {
+    IotaDerivationPath::new(0, 0, index).unwrap()
+}
+
+// public key and signature of a personal message, hex encoded
+fn session(ledger: &LedgerHardwareWallet) -> Result<(String, String), LedgerError> {
+    let public_key = ledger.get_public_key(&path(0))?;
+    let intent_msg = IntentMessage::new(Intent::personal_message(), b"Hello IOTA".to_vec());
+    let signed = ledger.sign_intent(&path(0), intent_msg, vec![])?;
+    Ok((
+        hex::encode(public_key.public_key.as_ref()),
+        hex::encode(signed.signature.as_ref()),
+    ))
+}
+
+fn record() -> (String, (String, String)) {
+    let buffer = SharedBuffer::default();
+    let emulator = TransportEmulator::new(EmulatorConfig::default()).unwrap();
+    let recording = RecordingTransport::new(emulator, buffer.clone()).unwrap();
+    let result = session(&LedgerHardwareWallet::with_transport(recording)).unwrap();
+
+    let trace = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    (trace, result)
+}
+
+fn replay(trace: &str) -> SharedReplay {
+    SharedReplay(Arc::new(
+        ReplayTransport::from_reader(Cursor::new(trace)).unwrap(),
+    ))
+}
+
+#[test]
+fn round_trip() {
+    let (trace, recorded) = record();
+    let replay = replay(&trace);
+    assert!(replay.0.remaining() > 0);
+
+    let replayed = session(&LedgerHardwareWallet::with_transport(replay.clone())).unwrap();
+    assert_eq!(replayed, recorded);
+    assert_eq!(replay.0.remaining(), 0);
+    replay.0.finish().unwrap();
+}
+
+#[test]
+fn incomplete_replay() {
+    let (trace, _) = record();
+    let replay = replay(&trace);
+
+    let ledger = LedgerHardwareWallet::with_transport(replay.clone());
+    ledger.get_public_key(&path(0)).unwrap();
+    assert!(matches!(
+        replay.0.finish(),
+        Err(LedgerError::ReplayMismatch { .. })
+    ));
+}
+
+#[test]
+fn mismatch() {
+    let (trace, _) = record();
+    let replay = replay(&trace);
+
+    let ledger = LedgerHardwareWallet::with_transport(replay.clone());
+    assert!(matches!(
+        ledger.get_public_key(&path(1)),
+        Err(LedgerError::ReplayMismatch { .. })
+    ));
+    // the replay stays failed, even for the recorded commands
+    assert!(matches!(
+        ledger.get_public_key(&path(0)),
+        Err(LedgerError::ReplayMismatch { .. })
+    ));
+    assert!(matches!(
+        replay.0.finish(),
+        Err(LedgerError::ReplayMismatch { index: 0, .. })
+    ));
+    assert_eq!(replay.0.remaining(), replay.0.entries().len());
+}
+
+// transport failing every exchange, like an unplugged device
+struct Unplugged;
+
+impl LedgerTransport for Unplugged {
+    fn exchange(
+        &self,
+        _apdu_command: &APDUCommand<Vec<u8>>,
+    ) -> Result<APDUAnswer<Vec<u8>>, LedgerError> {
+        Err(LedgerError::TransportError)
+    }
+}
+
+#[test]
+fn failed_exchange() {
+    let buffer = SharedBuffer::default();
+    let recording = RecordingTransport::new(Unplugged, buffer.clone()).unwrap();
+    let ledger = LedgerHardwareWallet::with_transport(recording);
+    assert!(ledger.get_public_key(&path(0)).is_err());
+
+    let trace = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert!(trace.lines().any(|line| line.starts_with('>')));
+    assert!(trace.lines().last().unwrap().ends_with(" error"));
+
+    let replay = replay(&trace);
+    assert_eq!(replay.0.entries().last().unwrap().answer, None);
+    let ledger = LedgerHardwareWallet::with_transport(replay.clone());
+    assert!(matches!(
+        ledger.get_public_key(&path(0)),
+        Err(LedgerError::TransportError)
+    ));
+    replay.0.finish().unwrap();
+}
@@ -3,12 +3,12 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum LedgerTCPError {
     /// Device not found error
-    #[error("Ledger connect error")]
-    ConnectError,
+    #[error("Ledger connect error: {0}")]
+    ConnectError(#[source] std::io::Error),
     /// zemu reponse error
     #[error("TCP response error")]
     ResponseError,
-    /// Inner error
-    #[error("Ledger inner error")]
-    InnerError,
+    /// I/O error while talking to the server
+    #[error("TCP i/o error: {0}")]
+    Io(#[from] std::io::Error),
 }
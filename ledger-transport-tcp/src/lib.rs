@@ -1,6 +1,8 @@
 use std::{
-    io::{Read, Write},
-    net::TcpStream,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Mutex, PoisonError},
+    time::Duration,
 };
 
 use ledger_transport::{APDUAnswer, APDUCommand};
@@ -10,61 +12,197 @@ pub use errors::LedgerTCPError;
 
 pub type Callback = fn(apdu_command: &APDUCommand<Vec<u8>>, apdu_answer: &APDUAnswer<Vec<u8>>);
 
+/// Timeouts applied to the TCP connection, `None` blocks indefinitely
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TcpTimeouts {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>,
+    pub write: Option<Duration>,
+}
+
+impl Default for TcpTimeouts {
+    fn default() -> Self {
+        TcpTimeouts {
+            connect: Some(Duration::from_secs(5)),
+            // generous, answers may wait for a confirmation on the (simulated) device
+            read: Some(Duration::from_secs(300)),
+            write: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
 pub struct TransportTCP {
     url: String,
+    timeouts: TcpTimeouts,
     callback: Option<Callback>,
+    stream: Mutex<Option<TcpStream>>,
 }
 
 impl TransportTCP {
     pub fn new(host: &str, port: u16, callback: Option<Callback>) -> Self {
+        Self::with_timeouts(host, port, TcpTimeouts::default(), callback)
+    }
+
+    pub fn with_timeouts(
+        host: &str,
+        port: u16,
+        timeouts: TcpTimeouts,
+        callback: Option<Callback>,
+    ) -> Self {
         Self {
             url: format!("{host}:{port}"),
+            timeouts,
             callback,
+            stream: Mutex::new(None),
         }
     }
 
-    fn request(raw_command: &[u8], stream: &mut TcpStream) -> Result<Vec<u8>, std::io::Error> {
+    fn connect(&self) -> Result<TcpStream, io::Error> {
+        let stream = match self.timeouts.connect {
+            Some(timeout) => {
+                let mut last_error = io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("could not resolve {}", self.url),
+                );
+                let mut connected = None;
+                for addr in self.url.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&addr, timeout) {
+                        Ok(stream) => {
+                            connected = Some(stream);
+                            break;
+                        }
+                        Err(e) => last_error = e,
+                    }
+                }
+                connected.ok_or(last_error)?
+            }
+            None => TcpStream::connect(&self.url)?,
+        };
+
+        stream.set_read_timeout(self.timeouts.read)?;
+        stream.set_write_timeout(self.timeouts.write)?;
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+
+    fn request(raw_command: &[u8], stream: &mut TcpStream) -> Result<Vec<u8>, RequestError> {
         // store length as 32bit big endian into array
         let send_length_bytes = (raw_command.len() as u32).to_be_bytes();
 
         // first send number of bytes
-        stream.write_all(&send_length_bytes[..])?;
+        stream
+            .write_all(&send_length_bytes[..])
+            .map_err(RequestError::Write)?;
 
         // then send bytes
-        stream.write_all(raw_command)?;
+        stream.write_all(raw_command).map_err(RequestError::Write)?;
 
         let mut rcv_length_bytes = [0u8; 4];
 
         // first read number of bytes
-        stream.read_exact(&mut rcv_length_bytes)?;
+        stream
+            .read_exact(&mut rcv_length_bytes)
+            .map_err(RequestError::Read)?;
 
         // convert bytes to big endian (+2 for return code)
         let rcv_length = u32::from_be_bytes(rcv_length_bytes) + 2;
 
         let mut buf = vec![0u8; rcv_length as usize];
-        stream.read_exact(&mut buf)?;
+        stream.read_exact(&mut buf).map_err(RequestError::Read)?;
         Ok(buf)
     }
 
+    // sends the command on the cached connection, connecting first if needed
+    fn request_on(
+        &self,
+        stream: &mut Option<TcpStream>,
+        raw_command: &[u8],
+    ) -> Result<Vec<u8>, RequestError> {
+        if stream.as_ref().is_some_and(is_stale) {
+            log::debug!("connection to {} was closed, reconnecting", &self.url);
+            *stream = None;
+        }
+        if stream.is_none() {
+            *stream = Some(self.connect().map_err(RequestError::Connect)?);
+            log::debug!("successfully connected to server {}", &self.url);
+        }
+
+        let result = TransportTCP::request(raw_command, stream.as_mut().expect("connected"));
+        if result.is_err() {
+            // the stream is in an undefined state, never reuse it
+            *stream = None;
+        }
+        result
+    }
+
     pub fn exchange(
         &self,
         command: &APDUCommand<Vec<u8>>,
     ) -> Result<APDUAnswer<Vec<u8>>, LedgerTCPError> {
         let raw_command = command.serialize();
 
-        let mut stream = TcpStream::connect(&self.url).map_err(|_| LedgerTCPError::ConnectError)?;
+        let mut stream = self.stream.lock().unwrap_or_else(PoisonError::into_inner);
+        let reused = stream.is_some();
+
+        let raw_answer = match self.request_on(&mut stream, &raw_command) {
+            // the command didn't get through, so resending can't run it twice.
+            // Once it is written the server may have processed it, a resend
+            // would desync the blocks protocol.
+            Err(RequestError::Write(e)) if reused && is_disconnect(&e) => {
+                log::debug!("connection to {} lost ({e}), reconnecting", &self.url);
+                self.request_on(&mut stream, &raw_command)
+            }
+            result => result,
+        }?;
+        drop(stream);
 
-        log::debug!("successfully connected to server {}", &self.url);
-
-        let raw_answer = TransportTCP::request(&raw_command, &mut stream)
-            .map_err(|_| LedgerTCPError::InnerError)?;
         let answer =
             APDUAnswer::from_answer(raw_answer).map_err(|_| LedgerTCPError::ResponseError)?;
 
-        if self.callback.is_some() {
-            (self.callback.unwrap())(command, &answer);
+        if let Some(callback) = self.callback {
+            callback(command, &answer);
         }
 
         Ok(answer)
     }
 }
+
+// how far a request got before it failed
+enum RequestError {
+    Connect(io::Error),
+    Write(io::Error),
+    Read(io::Error),
+}
+
+impl From<RequestError> for LedgerTCPError {
+    fn from(e: RequestError) -> Self {
+        match e {
+            RequestError::Connect(e) => LedgerTCPError::ConnectError(e),
+            RequestError::Write(e) | RequestError::Read(e) => LedgerTCPError::Io(e),
+        }
+    }
+}
+
+// an idle connection closed by the server reads EOF (or unexpected data)
+// right away, a live one would block
+fn is_stale(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return true;
+    }
+    let idle = matches!(
+        stream.peek(&mut [0u8; 1]),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock
+    );
+    !idle || stream.set_nonblocking(false).is_err()
+}
+
+fn is_disconnect(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::UnexpectedEof
+    )
+}
@@ -0,0 +1,127 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use ledger_transport::APDUCommand;
+use ledger_transport_tcp::{LedgerTCPError, TcpTimeouts, TransportTCP};
+
+fn command() -> APDUCommand<Vec<u8>> {
+    APDUCommand {
+        cla: 0x00,
+        ins: 0x03,
+        p1: 0,
+        p2: 0,
+        data: vec![1, 2, 3],
+    }
+}
+
+// reads one command, returns `false` once the client closed the connection
+fn read_command(stream: &mut TcpStream) -> bool {
+    let mut length = [0u8; 4];
+    if stream.read_exact(&mut length).is_err() {
+        return false;
+    }
+    let mut command = vec![0u8; u32::from_be_bytes(length) as usize];
+    stream.read_exact(&mut command).is_ok()
+}
+
+fn answer_ok(stream: &mut TcpStream) {
+    // empty answer with status word 0x9000
+    stream.write_all(&[0, 0, 0, 0, 0x90, 0x00]).unwrap();
+}
+
+struct Server {
+    port: u16,
+    connections: Arc<AtomicUsize>,
+    commands: Arc<AtomicUsize>,
+}
+
+// serves connections one after another, `handle` gets the connection number
+// and the number of commands received on it so far
+fn serve(handle: impl Fn(usize, usize, &mut TcpStream) -> bool + Send + 'static) -> Server {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let commands = Arc::new(AtomicUsize::new(0));
+
+    let (server_connections, server_commands) = (connections.clone(), commands.clone());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let connection = server_connections.fetch_add(1, Ordering::SeqCst);
+            let mut received = 0;
+            while read_command(&mut stream) {
+                server_commands.fetch_add(1, Ordering::SeqCst);
+                if !handle(connection, received, &mut stream) {
+                    break;
+                }
+                received += 1;
+            }
+        }
+    });
+
+    Server {
+        port,
+        connections,
+        commands,
+    }
+}
+
+#[test]
+fn reconnect_after_server_closed() {
+    // the first connection is closed after one answer
+    let server = serve(|connection, _, stream| {
+        answer_ok(stream);
+        connection > 0
+    });
+    let transport = TransportTCP::new("127.0.0.1", server.port, None);
+
+    assert_eq!(transport.exchange(&command()).unwrap().retcode(), 0x9000);
+    // give the server time to close the connection
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(transport.exchange(&command()).unwrap().retcode(), 0x9000);
+    assert_eq!(transport.exchange(&command()).unwrap().retcode(), 0x9000);
+
+    assert_eq!(server.connections.load(Ordering::SeqCst), 2);
+    assert_eq!(server.commands.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn read_timeout_is_not_resent() {
+    // only the first command gets an answer
+    let server = serve(|_, received, stream| {
+        if received == 0 {
+            answer_ok(stream);
+        }
+        true
+    });
+    let timeouts = TcpTimeouts {
+        read: Some(Duration::from_millis(200)),
+        ..Default::default()
+    };
+    let transport = TransportTCP::with_timeouts("127.0.0.1", server.port, timeouts, None);
+
+    assert_eq!(transport.exchange(&command()).unwrap().retcode(), 0x9000);
+    match transport.exchange(&command()) {
+        Err(LedgerTCPError::Io(e)) => assert!(
+            matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            ),
+            "{e}"
+        ),
+        other => panic!("expected a read timeout, got {other:?}"),
+    }
+
+    // the command may have been processed, so it is not sent again
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(server.connections.load(Ordering::SeqCst), 1);
+    assert_eq!(server.commands.load(Ordering::SeqCst), 2);
+}